@@ -1,11 +1,27 @@
 extern crate ipfsapi;
 
+use ipfsapi::ls::FileType;
 use ipfsapi::IpfsApi;
 
-fn main() {
-    //let api = IpfsApi::new("127.0.0.1", 5001);
-    //let hash = api.name_resolve("gkbrk.com").unwrap();
-    //let website_stats = api.object_stats(&hash[6..]);
+/// Adds up the sizes of the files under a UnixFS path.
+fn size(api: &IpfsApi, path: &str) -> ipfsapi::Result<u64> {
+    let mut total = 0;
 
-    //println!("{:?}", website_stats);
+    for entry in api.ls_stream(path)? {
+        let entry = entry?;
+        total += match entry.file_type {
+            FileType::Directory => size(api, &entry.hash.to_string())?,
+            _ => entry.size,
+        };
+    }
+
+    Ok(total)
+}
+
+fn main() -> ipfsapi::Result<()> {
+    let api = IpfsApi::new("127.0.0.1", 5001);
+    let path = api.name_resolve("gkbrk.com")?;
+
+    println!("{}: {} bytes", path, size(&api, &path)?);
+    Ok(())
 }
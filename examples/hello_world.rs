@@ -1,14 +1,16 @@
 extern crate ipfsapi;
 
 use ipfsapi::IpfsApi;
+use std::io::Read;
 
 fn main() {
     let api = IpfsApi::new("127.0.0.1", 5001);
 
-    let bytes = api
+    let mut stream = api
         .cat("QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u")
         .unwrap();
-    let data = String::from_utf8(bytes.collect()).unwrap();
+    let mut data = String::new();
+    stream.read_to_string(&mut data).unwrap();
 
     println!("{}", data);
 }
@@ -0,0 +1,629 @@
+//! Types for adding files to IPFS with [`IpfsApi::add`] and
+//! [`IpfsApi::add_path`].
+
+use crate::cid::{Cid, Version};
+use crate::multipart::{Multipart, Part};
+use crate::unixfs::ImportOptions;
+use crate::{Error, IpfsApi, Result};
+use serde_json::Value;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+/// Options of the `/api/v0/add` endpoint.
+///
+/// Options that are not set are left to the defaults of the daemon.
+///
+/// ```rust
+/// use ipfsapi::add::AddOptions;
+/// use ipfsapi::cid::Version;
+///
+/// let opts = AddOptions::new()
+///     .cid_version(Version::V1)
+///     .raw_leaves(true)
+///     .pin(false);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AddOptions {
+    cid_version: Option<Version>,
+    raw_leaves: Option<bool>,
+    chunker: Option<String>,
+    hash: Option<String>,
+    pin: Option<bool>,
+    only_hash: Option<bool>,
+    inline: Option<bool>,
+    trickle: Option<bool>,
+    pub(crate) wrap_with_directory: Option<bool>,
+    pub(crate) to_files: Option<String>,
+    pub(crate) hidden: bool,
+    pub(crate) ignore: Vec<String>,
+    pub(crate) follow_symlinks: bool,
+}
+
+impl AddOptions {
+    /// Creates options that use the defaults of the daemon.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the CID version. Version 1 also enables raw leaves, unless they
+    /// are disabled with [`raw_leaves`](Self::raw_leaves).
+    #[must_use]
+    pub fn cid_version(mut self, version: Version) -> Self {
+        self.cid_version = Some(version);
+        self
+    }
+
+    /// Stores the leaves of the DAG as raw blocks instead of UnixFS nodes.
+    #[must_use]
+    pub fn raw_leaves(mut self, raw_leaves: bool) -> Self {
+        self.raw_leaves = Some(raw_leaves);
+        self
+    }
+
+    /// Sets the chunking algorithm, such as `size-262144` or `rabin`.
+    #[must_use]
+    pub fn chunker(mut self, chunker: &str) -> Self {
+        self.chunker = Some(chunker.into());
+        self
+    }
+
+    /// Sets the hash function, such as `sha2-256` or `blake2b-256`.
+    #[must_use]
+    pub fn hash(mut self, hash: &str) -> Self {
+        self.hash = Some(hash.into());
+        self
+    }
+
+    /// Sets whether the added files are pinned.
+    #[must_use]
+    pub fn pin(mut self, pin: bool) -> Self {
+        self.pin = Some(pin);
+        self
+    }
+
+    /// Only computes the CIDs, without writing anything to the repository.
+    #[must_use]
+    pub fn only_hash(mut self, only_hash: bool) -> Self {
+        self.only_hash = Some(only_hash);
+        self
+    }
+
+    /// Inlines small blocks into their CIDs.
+    #[must_use]
+    pub fn inline(mut self, inline: bool) -> Self {
+        self.inline = Some(inline);
+        self
+    }
+
+    /// Uses the trickle DAG layout instead of the balanced one.
+    #[must_use]
+    pub fn trickle(mut self, trickle: bool) -> Self {
+        self.trickle = Some(trickle);
+        self
+    }
+
+    /// Wraps the added files in a directory.
+    #[must_use]
+    pub fn wrap_with_directory(mut self, wrap: bool) -> Self {
+        self.wrap_with_directory = Some(wrap);
+        self
+    }
+
+    /// Copies the added files to the given MFS path.
+    #[must_use]
+    pub fn to_files(mut self, path: &str) -> Self {
+        self.to_files = Some(path.into());
+        self
+    }
+
+    /// Includes files and directories whose names start with a dot. Only
+    /// used by [`IpfsApi::add_path`].
+    #[must_use]
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Skips the entries that match a glob pattern, such as `*.log` or
+    /// `target/**`. Only used by [`IpfsApi::add_path`].
+    ///
+    /// Patterns without a `/` are matched against the name of each entry,
+    /// and other patterns against its path relative to the added directory.
+    /// `*` and `?` do not match `/`, while `**` does.
+    #[must_use]
+    pub fn ignore(mut self, pattern: &str) -> Self {
+        self.ignore.push(pattern.into());
+        self
+    }
+
+    /// Adds the files and directories that symbolic links point to, instead
+    /// of the links themselves. Only used by [`IpfsApi::add_path`].
+    #[must_use]
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Returns the query parameters for the options that are set.
+    pub(crate) fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+
+        let mut push = |name, value: Option<String>| {
+            if let Some(value) = value {
+                query.push((name, value));
+            }
+        };
+
+        push("cid-version", self.cid_version.map(|v| v.as_arg().into()));
+        push("raw-leaves", self.raw_leaves.map(|v| v.to_string()));
+        push("chunker", self.chunker.clone());
+        push("hash", self.hash.clone());
+        push("pin", self.pin.map(|v| v.to_string()));
+        push("only-hash", self.only_hash.map(|v| v.to_string()));
+        push("inline", self.inline.map(|v| v.to_string()));
+        push("trickle", self.trickle.map(|v| v.to_string()));
+        push(
+            "wrap-with-directory",
+            self.wrap_with_directory.map(|v| v.to_string()),
+        );
+        push("to-files", self.to_files.clone());
+
+        query
+    }
+
+    /// Returns the options of the offline importer that compute the same
+    /// CIDs as the daemon, or `None` if some option is not supported by the
+    /// importer. Options that are not set use the defaults of Kubo.
+    pub(crate) fn import_options(&self) -> Option<ImportOptions> {
+        let mut opts = ImportOptions::new();
+
+        if let Some(version) = self.cid_version {
+            opts = opts.cid_version(version);
+        }
+        if let Some(raw_leaves) = self.raw_leaves {
+            opts = opts.raw_leaves(raw_leaves);
+        }
+        match self.chunker.as_deref() {
+            None | Some("default") => {}
+            Some(chunker) => {
+                let size = chunker.strip_prefix("size-")?.parse().ok()?;
+                if size == 0 {
+                    return None;
+                }
+                opts = opts.chunk_size(size);
+            }
+        }
+
+        let sha2_256 = matches!(self.hash.as_deref(), None | Some("sha2-256"));
+        let inline = self.inline.unwrap_or(false);
+        let trickle = self.trickle.unwrap_or(false);
+        if !sha2_256 || inline || trickle {
+            return None;
+        }
+
+        Some(opts)
+    }
+}
+
+/// An entry added by [`IpfsApi::add`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AddResponse {
+    /// The name of the entry, or its CID if it was added without a name.
+    pub name: String,
+
+    /// The CID of the entry.
+    pub hash: Cid,
+
+    /// The cumulative size of the entry's DAG, in bytes.
+    pub size: u64,
+}
+
+impl AddResponse {
+    /// Parses an entry of the response. Returns `None` for progress reports.
+    pub(crate) fn from_json(val: &Value) -> Result<Option<Self>> {
+        if val.get("Hash").is_none() && val.get("Bytes").is_some() {
+            return Ok(None);
+        }
+
+        let string = |field: &str| {
+            val[field].as_str().map(String::from).ok_or_else(|| {
+                Error::response(&format!("{} is not a string", field))
+            })
+        };
+
+        // The daemon sends the size as a string.
+        let size = match &val["Size"] {
+            Value::String(s) => s.parse().ok(),
+            other => other.as_u64(),
+        }
+        .ok_or_else(|| Error::response("Size is not a number"))?;
+
+        Ok(Some(Self {
+            name: string("Name")?,
+            hash: string("Hash")?.parse()?,
+            size,
+        }))
+    }
+}
+
+/// The result of [`IpfsApi::add_path`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AddPathResponse {
+    /// The added file or directory, or the wrapping directory if one was
+    /// requested with [`AddOptions::wrap_with_directory`].
+    pub root: AddResponse,
+
+    /// Every added entry, including the root.
+    pub entries: Vec<AddResponse>,
+}
+
+/// Collects the entries of an `add` response.
+pub(crate) fn parse_entries(
+    lines: impl Iterator<Item = Result<Value>>,
+) -> Result<Vec<AddResponse>> {
+    let mut entries = Vec::new();
+
+    for line in lines {
+        if let Some(entry) = AddResponse::from_json(&line?)? {
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// The progress of an upload, reported by
+/// [`IpfsApi::add_with_upload_progress`] and
+/// [`IpfsApi::add_path_with_upload_progress`].
+///
+/// It counts the bytes sent to the daemon, not the bytes that it has
+/// imported. The daemon chunks and hashes the contents as they arrive, so
+/// the call can still take a while after every byte has been reported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UploadProgress {
+    /// The name of the file that is being sent, which is empty for
+    /// [`IpfsApi::add_with_upload_progress`].
+    pub name: String,
+
+    /// The number of bytes of this file sent so far.
+    pub bytes: u64,
+
+    /// The number of bytes of all files sent so far.
+    pub total: u64,
+
+    /// The total size of all files, if it is known in advance.
+    pub total_size: Option<u64>,
+}
+
+/// The progress shared between the readers of the files of an upload.
+#[derive(Default)]
+struct Counter {
+    file: usize,
+    bytes: u64,
+    total: u64,
+    changed: bool,
+}
+
+/// Counts the bytes read from the contents of a file.
+struct CountingReader<R> {
+    inner: R,
+    file: usize,
+    bytes: u64,
+    counter: Arc<Mutex<Counter>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+
+        if n > 0 {
+            self.bytes += n as u64;
+
+            let mut counter = self.counter.lock().unwrap();
+            counter.file = self.file;
+            counter.bytes = self.bytes;
+            counter.total += n as u64;
+            counter.changed = true;
+        }
+
+        Ok(n)
+    }
+}
+
+/// Reports the progress counted while the body of an upload is read.
+struct ProgressReader<'a, R, F> {
+    inner: R,
+    upload: &'a Upload<'a>,
+    on_progress: F,
+}
+
+impl<R: Read, F: FnMut(&UploadProgress)> Read for ProgressReader<'_, R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+
+        let progress = {
+            let mut counter = self.upload.counter.lock().unwrap();
+            if !counter.changed {
+                return Ok(n);
+            }
+            counter.changed = false;
+
+            UploadProgress {
+                name: self.upload.names[counter.file].clone(),
+                bytes: counter.bytes,
+                total: counter.total,
+                total_size: self.upload.total_size,
+            }
+        };
+
+        (self.on_progress)(&progress);
+        Ok(n)
+    }
+}
+
+/// The body of a request to the `add` endpoint, whose progress is counted
+/// as it is sent.
+///
+/// The progress is measured on the client, because the progress reports of
+/// the daemon can only be read while uploading over a full-duplex connection.
+/// Asking for them otherwise can stall large uploads once the reports fill
+/// the socket buffers.
+pub(crate) struct Upload<'a> {
+    body: Option<Multipart<'a>>,
+    names: Vec<String>,
+    counter: Arc<Mutex<Counter>>,
+    total_size: Option<u64>,
+}
+
+impl<'a> Upload<'a> {
+    pub(crate) fn new(total_size: Option<u64>) -> Self {
+        Self {
+            body: Some(Multipart::new()),
+            names: Vec::new(),
+            counter: Arc::default(),
+            total_size,
+        }
+    }
+
+    /// Returns a reader for the contents of the file `name`, that counts the
+    /// bytes read from it.
+    pub(crate) fn counted<R: Read + Send + 'a>(
+        &mut self,
+        name: &str,
+        inner: R,
+    ) -> impl Read + Send + 'a {
+        self.names.push(name.into());
+
+        CountingReader {
+            inner,
+            file: self.names.len() - 1,
+            bytes: 0,
+            counter: self.counter.clone(),
+        }
+    }
+
+    pub(crate) fn add_part(&mut self, part: Part<'a>) {
+        if let Some(body) = &mut self.body {
+            body.add_part(part);
+        }
+    }
+
+    /// Sends the upload, and returns the added entries.
+    pub(crate) fn send(
+        mut self,
+        api: &IpfsApi,
+        opts: &AddOptions,
+        on_progress: impl FnMut(&UploadProgress),
+    ) -> Result<Vec<AddResponse>> {
+        let body = self.body.take().unwrap_or_default();
+
+        let mut req = api.post("add").set("Content-Type", &body.content_type());
+        for (name, value) in opts.query() {
+            req = req.query(name, &value);
+        }
+
+        let reader = ProgressReader {
+            inner: body.into_reader(),
+            upload: &self,
+            on_progress,
+        };
+
+        let resp = req.send(reader)?;
+        parse_entries(resp.into_json_lines())
+    }
+}
+
+impl IpfsApi {
+    /// Adds a file to IPFS, streaming its contents from a reader. Returns the
+    /// added entries, which include a wrapping directory if one was requested
+    /// with [`AddOptions::wrap_with_directory`].
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// use ipfsapi::add::AddOptions;
+    /// use ipfsapi::cid::Version;
+    ///
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let opts = AddOptions::new().cid_version(Version::V1);
+    /// let added = api.add(&b"Hello world"[..], &opts)?;
+    /// println!("{}", added[0].hash);
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/add` API.
+    pub fn add(
+        &self,
+        data: impl Read + Send,
+        opts: &AddOptions,
+    ) -> Result<Vec<AddResponse>> {
+        self.add_with_upload_progress(data, opts, |_| {})
+    }
+
+    /// Adds a file to IPFS like [`add`](Self::add), and calls `on_progress`
+    /// as its contents are sent to the daemon. The progress is that of the
+    /// upload, see [`UploadProgress`].
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// use ipfsapi::add::AddOptions;
+    /// use std::fs::File;
+    ///
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let file = File::open("disk.img")?;
+    /// let added = api.add_with_upload_progress(file, &AddOptions::new(), |p| {
+    ///     eprint!("\r{} bytes sent", p.total);
+    /// })?;
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    pub fn add_with_upload_progress(
+        &self,
+        data: impl Read + Send,
+        opts: &AddOptions,
+        on_progress: impl FnMut(&UploadProgress),
+    ) -> Result<Vec<AddResponse>> {
+        crate::rand::feed_event("add");
+
+        let mut upload = Upload::new(None);
+        let data = upload.counted("", data);
+        upload.add_part(
+            Part::new("file", data)
+                .filename("")
+                .header("Content-Type", "application/octet-stream"),
+        );
+
+        upload.send(self, opts, on_progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AddOptions;
+    use crate::cid::Version;
+    use crate::testing::{MockResponse, MockServer};
+    use crate::unixfs::{self, ImportOptions};
+    use crate::Error;
+    use serde_json::json;
+
+    #[test]
+    fn test_add() {
+        let server = MockServer::start();
+        server.on(
+            "add",
+            MockResponse::ndjson([
+                json!({"Name": "", "Bytes": 11}),
+                json!({
+                    "Name": "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e",
+                    "Hash": "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e",
+                    "Size": "11",
+                }),
+            ]),
+        );
+
+        let opts = AddOptions::new()
+            .cid_version(Version::V1)
+            .raw_leaves(true)
+            .chunker("size-1024")
+            .pin(false)
+            .to_files("/hello.txt");
+        let added = server.api().add(&b"Hello world"[..], &opts).unwrap();
+
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].size, 11);
+        assert_eq!(
+            added[0].hash.to_string(),
+            "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e"
+        );
+
+        let request = &server.requests()[0];
+        assert_eq!(
+            request.query,
+            [
+                ("cid-version", "1"),
+                ("raw-leaves", "true"),
+                ("chunker", "size-1024"),
+                ("pin", "false"),
+                ("to-files", "/hello.txt"),
+            ]
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+        );
+
+        let parts = request.parts().unwrap();
+        assert_eq!(parts[0].name, "file");
+        assert_eq!(parts[0].body, b"Hello world");
+    }
+
+    #[test]
+    fn test_add_stream_error() {
+        let server = MockServer::start();
+        server.on(
+            "add",
+            MockResponse::ndjson([]).stream_error("blockstore: disk full"),
+        );
+
+        match server.api().add(&b"Hello world"[..], &AddOptions::new()) {
+            Err(Error::Stream(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_add_with_upload_progress() {
+        let server = MockServer::start();
+        server.on(
+            "add",
+            MockResponse::ndjson([json!({
+                "Name": "QmSGkFU26J43H5ToS5VwoyS1eqP41F6VU1WUyNaNui4gXg",
+                "Hash": "QmSGkFU26J43H5ToS5VwoyS1eqP41F6VU1WUyNaNui4gXg",
+                "Size": "200055",
+            })]),
+        );
+
+        let data = vec![7; 200_000];
+        let mut progress = Vec::new();
+        server
+            .api()
+            .add_with_upload_progress(&data[..], &AddOptions::new(), |p| {
+                progress.push(p.clone())
+            })
+            .unwrap();
+
+        assert!(progress.len() > 1);
+        assert!(progress.windows(2).all(|w| w[0].total < w[1].total));
+
+        let last = progress.last().unwrap();
+        assert_eq!(last.name, "");
+        assert_eq!(last.bytes, 200_000);
+        assert_eq!(last.total, 200_000);
+        assert_eq!(last.total_size, None);
+
+        // The daemon's progress reports are not requested.
+        assert_eq!(server.requests()[0].query_value("progress"), None);
+    }
+
+    #[test]
+    fn test_import_options() {
+        let data = &b"hello world\n"[..];
+        let opts = AddOptions::new().cid_version(Version::V1).chunker("size-4");
+        let import = opts.import_options().unwrap();
+        let expected =
+            ImportOptions::new().cid_version(Version::V1).chunk_size(4);
+        assert_eq!(
+            unixfs::hash(data, &import).unwrap(),
+            unixfs::hash(data, &expected).unwrap()
+        );
+
+        for unsupported in [
+            AddOptions::new().chunker("rabin"),
+            AddOptions::new().chunker("size-0"),
+            AddOptions::new().hash("blake2b-256"),
+            AddOptions::new().inline(true),
+            AddOptions::new().trickle(true),
+        ] {
+            assert!(unsupported.import_options().is_none());
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use crate::add::{AddOptions, AddPathResponse, Upload, UploadProgress};
+use crate::multipart::Part;
+use crate::transport::percent_encode;
+use crate::{Error, IpfsApi, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// A local entry to upload, in the form the daemon expects it.
+pub(crate) enum Entry {
+    File(PathBuf),
+    Directory,
+    Symlink(String),
+}
+
+impl Entry {
+    fn into_part<'a>(self, name: &str, upload: &mut Upload<'a>) -> Part<'a> {
+        // The daemon unescapes the filenames, so that they can contain any
+        // character.
+        let mut filename = String::new();
+        percent_encode(&mut filename, name);
+
+        let (part, content_type) = match self {
+            Entry::File(path) => (
+                Part::new(
+                    "file",
+                    upload.counted(name, LazyFile { path, file: None }),
+                ),
+                "application/octet-stream",
+            ),
+            Entry::Directory => {
+                (Part::bytes("file", Vec::new()), "application/x-directory")
+            }
+            Entry::Symlink(target) => (
+                Part::bytes("file", target.into_bytes()),
+                "application/symlink",
+            ),
+        };
+
+        part.filename(&filename)
+            .header("Content-Type", content_type)
+    }
+}
+
+/// A file that is opened when it is first read, so that large directories
+/// do not use up all file descriptors.
+struct LazyFile {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl Read for LazyFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.file {
+            Some(file) => file.read(buf),
+            None => self.file.insert(File::open(&self.path)?).read(buf),
+        }
+    }
+}
+
+fn invalid(msg: String) -> Error {
+    Error::InvalidInput(msg)
+}
+
+/// Collects the entries under a path, parents before their children.
+struct Walker<'a> {
+    opts: &'a AddOptions,
+    entries: Vec<(String, Entry)>,
+    ancestors: Vec<PathBuf>,
+    size: u64,
+}
+
+impl Walker<'_> {
+    /// Visits the entry at `path`, named `name` in the upload and `rel`
+    /// relative to the added directory.
+    fn visit(&mut self, path: &Path, name: String, rel: &str) -> Result<()> {
+        let meta = if self.opts.follow_symlinks {
+            std::fs::metadata(path)?
+        } else {
+            std::fs::symlink_metadata(path)?
+        };
+
+        if meta.file_type().is_symlink() {
+            let target = std::fs::read_link(path)?;
+            let target = target.to_str().ok_or_else(|| {
+                invalid(format!("{} is not valid UTF-8", target.display()))
+            })?;
+            self.entries.push((name, Entry::Symlink(target.into())));
+        } else if meta.is_file() {
+            self.size += meta.len();
+            self.entries.push((name, Entry::File(path.into())));
+        } else if meta.is_dir() {
+            let canonical = std::fs::canonicalize(path)?;
+            if self.ancestors.contains(&canonical) {
+                let msg = format!("symlink cycle at {}", path.display());
+                return Err(invalid(msg));
+            }
+
+            let mut children = std::fs::read_dir(path)?
+                .collect::<std::io::Result<Vec<_>>>()?;
+            children.sort_by_key(|child| child.file_name());
+
+            self.entries.push((name.clone(), Entry::Directory));
+            self.ancestors.push(canonical);
+
+            for child in children {
+                let child_name =
+                    child.file_name().into_string().map_err(|n| {
+                        invalid(format!("{:?} is not valid UTF-8", n))
+                    })?;
+                let child_rel = if rel.is_empty() {
+                    child_name.clone()
+                } else {
+                    format!("{}/{}", rel, child_name)
+                };
+
+                if !self.skip(&child_name, &child_rel) {
+                    let child_path = format!("{}/{}", name, child_name);
+                    self.visit(&child.path(), child_path, &child_rel)?;
+                }
+            }
+
+            self.ancestors.pop();
+        } else {
+            let msg = format!("{} is not a file or directory", path.display());
+            return Err(invalid(msg));
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether an entry is excluded by the hidden or ignore filters.
+    fn skip(&self, name: &str, rel: &str) -> bool {
+        if !self.opts.hidden && name.starts_with('.') {
+            return true;
+        }
+
+        self.opts.ignore.iter().any(|pattern| {
+            let text = if pattern.contains('/') { rel } else { name };
+            let pattern: Vec<char> = pattern.chars().collect();
+            let text: Vec<char> = text.chars().collect();
+            glob_match(&pattern, &text)
+        })
+    }
+}
+
+/// Collects the entries under the local directory `path` with the filters of
+/// `opts`, named by their path relative to `path`, parents before their
+/// children.
+pub(crate) fn local_entries(
+    path: &Path,
+    opts: &AddOptions,
+) -> Result<Vec<(String, Entry)>> {
+    if !std::fs::metadata(path)?.is_dir() {
+        return Err(invalid(format!("{} is not a directory", path.display())));
+    }
+
+    let mut walker = Walker {
+        opts,
+        entries: Vec::new(),
+        ancestors: Vec::new(),
+        size: 0,
+    };
+    walker.visit(path, String::new(), "")?;
+
+    // The root is named "", so its children are named "/child".
+    let entries = walker.entries.into_iter().skip(1);
+    Ok(entries
+        .map(|(name, entry)| (name[1..].to_string(), entry))
+        .collect())
+}
+
+/// Matches a glob pattern, where `*` and `?` do not match `/`, `**` matches
+/// anything, and `**/` matches any number of directories.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => (0..=text.len())
+            .filter(|&i| i == 0 || text[i - 1] == '/')
+            .any(|i| glob_match(rest, &text[i..])),
+        ['*', '*', rest @ ..] => {
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| glob_match(rest, &text[i..])),
+        ['?', rest @ ..] => match text {
+            [c, text @ ..] if *c != '/' => glob_match(rest, text),
+            _ => false,
+        },
+        [p, rest @ ..] => match text {
+            [c, text @ ..] if c == p => glob_match(rest, text),
+            _ => false,
+        },
+    }
+}
+
+impl IpfsApi {
+    /// Adds a local file or directory to IPFS, including everything under
+    /// it. Returns the root entry and every added entry.
+    ///
+    /// Files and directories whose names start with a dot are skipped unless
+    /// [`AddOptions::hidden`] is set, as are the entries that match an
+    /// [`AddOptions::ignore`] pattern. Symbolic links are added as links
+    /// unless [`AddOptions::follow_symlinks`] is set.
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// use ipfsapi::add::AddOptions;
+    /// use ipfsapi::cid::Version;
+    ///
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let opts = AddOptions::new().cid_version(Version::V1).ignore("*.map");
+    /// let added = api.add_path("public", &opts)?;
+    /// println!("{}", added.root.hash);
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/add` API.
+    pub fn add_path(
+        &self,
+        path: impl AsRef<Path>,
+        opts: &AddOptions,
+    ) -> Result<AddPathResponse> {
+        self.add_path_with_upload_progress(path, opts, |_| {})
+    }
+
+    /// Adds a local file or directory to IPFS like
+    /// [`add_path`](Self::add_path), and calls `on_progress` as the contents
+    /// of the files are sent to the daemon. The progress is that of the
+    /// upload, see [`UploadProgress`], and includes the total size of the
+    /// files.
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// use ipfsapi::add::AddOptions;
+    ///
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let added = api.add_path_with_upload_progress("dist", &AddOptions::new(), |p| {
+    ///     let total_size = p.total_size.unwrap_or_default().max(1);
+    ///     eprint!("\r{}: {}%", p.name, p.total * 100 / total_size);
+    /// })?;
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    pub fn add_path_with_upload_progress(
+        &self,
+        path: impl AsRef<Path>,
+        opts: &AddOptions,
+        on_progress: impl FnMut(&UploadProgress),
+    ) -> Result<AddPathResponse> {
+        crate::rand::feed_event("add_path");
+
+        let path = path.as_ref();
+        let root_name = match path.file_name() {
+            Some(name) => name.to_owned(),
+            None => std::fs::canonicalize(path)?
+                .file_name()
+                .map(ToOwned::to_owned)
+                .unwrap_or_default(),
+        };
+        let root_name = root_name
+            .into_string()
+            .map_err(|n| invalid(format!("{:?} is not valid UTF-8", n)))?;
+
+        let mut walker = Walker {
+            opts,
+            entries: Vec::new(),
+            ancestors: Vec::new(),
+            size: 0,
+        };
+        walker.visit(path, root_name.clone(), "")?;
+
+        let mut upload = Upload::new(Some(walker.size));
+        for (name, entry) in walker.entries {
+            let part = entry.into_part(&name, &mut upload);
+            upload.add_part(part);
+        }
+
+        let entries = upload.send(self, opts, on_progress)?;
+
+        // The wrapping directory has no name.
+        let root_name = match opts.wrap_with_directory {
+            Some(true) => "",
+            _ => &root_name,
+        };
+        let root = entries
+            .iter()
+            .find(|entry| entry.name == root_name)
+            .or_else(|| entries.last())
+            .cloned()
+            .ok_or_else(|| Error::response("no entries were added"))?;
+
+        Ok(AddPathResponse { root, entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+    use crate::add::AddOptions;
+    use crate::cid::Version;
+    use crate::testing::{MockResponse, MockServer};
+    use crate::Error;
+    use serde_json::json;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        glob_match(&pattern, &text)
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(matches("*.log", "build.log"));
+        assert!(!matches("*.log", "build.log.gz"));
+        assert!(matches("?.txt", "a.txt"));
+        assert!(!matches("*.txt", "dir/a.txt"));
+        assert!(matches("dir/*.txt", "dir/a.txt"));
+        assert!(matches("dir/**", "dir/sub/a.txt"));
+        assert!(matches("**/a.txt", "a.txt"));
+        assert!(matches("dir/**/a.txt", "dir/x/y/a.txt"));
+        assert!(!matches("dir/**/a.txt", "dirx/a.txt"));
+    }
+
+    #[test]
+    fn test_add_path() {
+        let dir = std::env::temp_dir()
+            .join(format!("ipfsapi-add-path-{}", std::process::id()));
+        let root = dir.join("site");
+        std::fs::create_dir_all(root.join("sub dir")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join("index.html"), "<h1>Hi</h1>").unwrap();
+        std::fs::write(root.join("sub dir/a.txt"), "a").unwrap();
+        std::fs::write(root.join("sub dir/debug.log"), "log").unwrap();
+        std::fs::write(root.join(".git/HEAD"), "ref").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("index.html", root.join("home.html"))
+            .unwrap();
+
+        let site = "QmfH6wcHkDAw76erQUmN7S6JaoNUDJZR72jvnR41hdQQZj";
+        let server = MockServer::start();
+        server.on(
+            "add",
+            MockResponse::ndjson([
+                json!({
+                    "Name": "site/sub dir/a.txt",
+                    "Hash": "QmbyV4BASLJCFiCfKz37eNwFX8y6hefQ7LGpzvEwJQzNft",
+                    "Size": "9",
+                }),
+                json!({
+                    "Name": "site/sub dir",
+                    "Hash": "QmdGNhud6dAKjpYUj2vyXxPzznQ94cbihvBQ9gXEfxYxwj",
+                    "Size": "60",
+                }),
+                json!({
+                    "Name": "site/index.html",
+                    "Hash": "QmPL4juVjiWZEXK8fCUymPHfdWJv5V34gF7y4vhtXU43dz",
+                    "Size": "19",
+                }),
+                json!({"Name": "site", "Hash": site, "Size": "150"}),
+            ]),
+        );
+
+        let opts = AddOptions::new().cid_version(Version::V1).ignore("*.log");
+        let mut progress = Vec::new();
+        let added = server
+            .api()
+            .add_path_with_upload_progress(&root, &opts, |p| {
+                progress.push(p.clone())
+            })
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(added.root.name, "site");
+        assert_eq!(added.root.hash.to_string(), site);
+        assert_eq!(added.entries.len(), 4);
+
+        let names: Vec<&str> =
+            progress.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["site/index.html", "site/sub dir/a.txt"]);
+        assert_eq!(progress[1].total, 12);
+        assert_eq!(progress[1].total_size, Some(12));
+
+        let request = &server.requests()[0];
+        assert_eq!(request.query_value("cid-version"), Some("1"));
+
+        let parts: Vec<_> = request
+            .parts()
+            .unwrap()
+            .into_iter()
+            .map(|part| {
+                let content_type = part.header("Content-Type").unwrap().into();
+                (part.filename.unwrap(), content_type, part.body)
+            })
+            .collect();
+
+        let mut expected = vec![
+            ("site", "application/x-directory", ""),
+            (
+                "site%2Findex.html",
+                "application/octet-stream",
+                "<h1>Hi</h1>",
+            ),
+            ("site%2Fsub%20dir", "application/x-directory", ""),
+            ("site%2Fsub%20dir%2Fa.txt", "application/octet-stream", "a"),
+        ];
+        if cfg!(unix) {
+            let link =
+                ("site%2Fhome.html", "application/symlink", "index.html");
+            expected.insert(1, link);
+        }
+        let expected: Vec<(String, String, Vec<u8>)> = expected
+            .into_iter()
+            .map(|(n, t, b)| (n.into(), t.into(), b.into()))
+            .collect();
+        assert_eq!(parts, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_add_path_follow_symlinks() {
+        let dir = std::env::temp_dir()
+            .join(format!("ipfsapi-add-path-follow-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("files")).unwrap();
+        std::fs::write(dir.join("files/a.txt"), "a").unwrap();
+        std::os::unix::fs::symlink("..", dir.join("files/up")).unwrap();
+
+        let server = MockServer::start();
+        server.on(
+            "add",
+            MockResponse::ndjson([json!({
+                "Name": "x",
+                "Hash": "QmRQ353oFNqt8zfZ9X1HgRUszwv9RkEEwmMZZkbkYEsybn",
+                "Size": "1",
+            })]),
+        );
+
+        // Following the link to the parent directory would never end.
+        let opts = AddOptions::new().follow_symlinks(true);
+        let result = server.api().add_path(dir.join("files/up"), &opts);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+        assert!(server.requests().is_empty());
+    }
+}
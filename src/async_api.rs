@@ -0,0 +1,422 @@
+use crate::add::{AddOptions, AddResponse};
+use crate::cid::Cid;
+use crate::ls::LsEntry;
+use crate::multipart::async_body;
+use crate::stream::JsonLines;
+use crate::version::IpfsVersion;
+use crate::{Auth, Error, IpfsApi, Result};
+use bytes::Bytes;
+use futures_util::{future, Stream, TryStreamExt};
+use std::io::Cursor;
+use tokio::io::AsyncRead;
+
+/// An asynchronous version of [`IpfsApi`], for use from async runtimes such
+/// as tokio.
+///
+/// It is created with [`AsyncIpfsApi::new`] or
+/// [`IpfsApiBuilder::build_async`](crate::IpfsApiBuilder::build_async), and
+/// supports the same address, timeout, header and authentication settings as
+/// the blocking API.
+///
+/// ```rust,no_run
+/// # async fn run() -> ipfsapi::Result<()> {
+/// use futures_util::TryStreamExt;
+/// use ipfsapi::AsyncIpfsApi;
+///
+/// let api = AsyncIpfsApi::new("127.0.0.1", 5001)?;
+///
+/// let mut stream = api.cat("QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u").await?;
+/// while let Some(chunk) = stream.try_next().await? {
+///     print!("{}", String::from_utf8_lossy(&chunk));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct AsyncIpfsApi {
+    base_url: String,
+    client: reqwest::Client,
+    headers: Vec<(String, String)>,
+    auth: Option<Auth>,
+}
+
+impl AsyncIpfsApi {
+    /// Creates a new instance of the API.
+    ///
+    /// # Errors
+    /// Returns an error if the HTTP client cannot be initialized.
+    pub fn new(server: &str, port: u16) -> Result<Self> {
+        let base_url = format!("http://{}:{}/api/v0", server, port);
+        IpfsApi::builder().base_url(&base_url).build_async()
+    }
+
+    pub(crate) fn from_parts(
+        base_url: String,
+        client: reqwest::Client,
+        headers: Vec<(String, String)>,
+        auth: Option<Auth>,
+    ) -> Self {
+        Self {
+            base_url,
+            client,
+            headers,
+            auth,
+        }
+    }
+
+    /// Sends a POST request to an API endpoint and checks the status of the
+    /// response.
+    async fn call(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        body: Option<(String, reqwest::Body)>,
+    ) -> Result<reqwest::Response> {
+        let mut url = format!("{}/{}", self.base_url, endpoint);
+
+        if !query.is_empty() {
+            let query: Vec<(String, String)> = query
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            url.push('?');
+            url.push_str(&crate::transport::encode_query(&query));
+        }
+
+        let mut req = self.client.post(url);
+
+        for (name, value) in &self.headers {
+            req = req.header(name, value);
+        }
+
+        if let Some(auth) = &self.auth {
+            req = req.header("Authorization", auth.header_value());
+        }
+
+        if let Some((content_type, body)) = body {
+            req = req.header("Content-Type", content_type).body(body);
+        }
+
+        let resp = req.send().await?;
+        let status = resp.status();
+
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(Error::from_status(status.as_u16(), body));
+        }
+
+        Ok(resp)
+    }
+
+    async fn call_json(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        body: Option<(String, reqwest::Body)>,
+    ) -> Result<serde_json::Value> {
+        let resp = self.call(endpoint, query, body).await?;
+        Ok(serde_json::from_slice(&resp.bytes().await?)?)
+    }
+
+    /// Sends a request and returns the chunks of the response body. An
+    /// error reported by the daemon in the `X-Stream-Error` trailer is
+    /// returned as the last item.
+    async fn call_stream(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let resp = self.call(endpoint, query, None).await?;
+        let frames = http_body_util::BodyStream::new(reqwest::Body::from(resp));
+
+        Ok(frames.map_err(Error::from).try_filter_map(|frame| {
+            // A ready future keeps the stream `Unpin`, so that callers
+            // can use `try_next` on it directly.
+            let frame = match frame.into_data() {
+                Ok(data) => return future::ready(Ok(Some(data))),
+                Err(frame) => frame,
+            };
+
+            let stream_error = frame
+                .trailers_ref()
+                .and_then(|trailers| trailers.get("X-Stream-Error"));
+            future::ready(match stream_error {
+                Some(msg) => {
+                    let msg = String::from_utf8_lossy(msg.as_bytes());
+                    Err(Error::Stream(msg.into()))
+                }
+                None => Ok(None),
+            })
+        }))
+    }
+
+    /// Retrieves the contents of a file from the IPFS network as a stream of
+    /// chunks.
+    pub async fn cat(
+        &self,
+        path: impl Into<String>,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        crate::rand::feed_event("cat");
+        let path = path.into();
+        self.call_stream("cat", &[("arg", path.as_str())]).await
+    }
+
+    /// Retrieves the contents of a file from the IPFS network as an
+    /// [`AsyncRead`].
+    pub async fn cat_reader(
+        &self,
+        path: impl Into<String>,
+    ) -> Result<impl AsyncRead> {
+        let stream = self.cat(path).await?;
+        Ok(tokio_util::io::StreamReader::new(
+            stream.map_err(std::io::Error::from),
+        ))
+    }
+
+    /// Get a raw IPFS block as a stream of chunks. The CID can be a [`Cid`]
+    /// or its string form.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/block/get` API.
+    pub async fn block_get(
+        &self,
+        cid: impl TryInto<Cid, Error = impl Into<Error>>,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        crate::rand::feed_event("block_get");
+        let cid: Cid = cid.try_into().map_err(Into::into)?;
+        let cid = cid.to_string();
+        self.call_stream("block/get", &[("arg", cid.as_str())])
+            .await
+    }
+
+    /// Get a raw IPFS block, and check that it matches the multihash of its
+    /// CID.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/block/get` API.
+    pub async fn block_get_verified(
+        &self,
+        cid: impl TryInto<Cid, Error = impl Into<Error>>,
+    ) -> Result<Vec<u8>> {
+        let cid: Cid = cid.try_into().map_err(Into::into)?;
+        cid.hash().matches(b"")?;
+
+        let mut stream = std::pin::pin!(self.block_get(&cid).await?);
+        let mut block = Vec::new();
+        while let Some(chunk) = stream.try_next().await? {
+            block.extend_from_slice(&chunk);
+            if block.len() > crate::block_get::MAX_BLOCK_SIZE as usize {
+                break;
+            }
+        }
+
+        crate::block_get::verify(&cid, block)
+    }
+
+    /// Puts a raw block into IPFS.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/block/put` API.
+    pub async fn block_put(&self, data: &[u8]) -> Result<Cid> {
+        self.block_put_reader(Cursor::new(data.to_vec())).await
+    }
+
+    /// Puts a raw block into IPFS, streaming its contents from a reader.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/block/put` API.
+    pub async fn block_put_reader(
+        &self,
+        data: impl AsyncRead + Send + 'static,
+    ) -> Result<Cid> {
+        crate::rand::feed_event("block_put");
+
+        let body = async_body("data", None, &[], data);
+        let resp = self.call_json("block/put", &[], Some(body)).await?;
+        crate::block_put::parse_key(&resp)
+    }
+
+    /// Adds a file to IPFS, streaming its contents from a reader.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/add` API.
+    pub async fn add(
+        &self,
+        data: impl AsyncRead + Send + 'static,
+        opts: &AddOptions,
+    ) -> Result<Vec<AddResponse>> {
+        crate::rand::feed_event("add");
+
+        let headers = [("Content-Type", "application/octet-stream")];
+        let body = async_body("file", Some(""), &headers, data);
+
+        let query = opts.query();
+        let query: Vec<(&str, &str)> =
+            query.iter().map(|(n, v)| (*n, v.as_str())).collect();
+        let resp = self.call("add", &query, Some(body)).await?;
+
+        let body = resp.bytes().await?;
+        crate::add::parse_entries(JsonLines::new(&body[..]))
+    }
+
+    /// Lists the entries of a UnixFS directory.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/ls` API.
+    pub async fn ls(&self, path: impl Into<String>) -> Result<Vec<LsEntry>> {
+        let path = path.into();
+        let resp = self
+            .call_json("ls", &[("arg", path.as_str())], None)
+            .await?;
+        crate::ls::parse_objects(&resp)
+    }
+
+    /// Get the version from the IPFS daemon.
+    pub async fn version(&self) -> Result<IpfsVersion> {
+        let resp = self.call_json("version", &[], None).await?;
+        Ok(IpfsVersion::new(resp))
+    }
+
+    /// Resolve an IPNS hash or a domain name
+    pub async fn name_resolve(&self, name: &str) -> Result<String> {
+        let query = [("arg", name)];
+        let resp = self.call_json("name/resolve", &query, None).await?;
+        crate::ipns_name_resolve::parse_path(&resp)
+    }
+
+    /// Publish an IPFS hash in IPNS.
+    pub async fn name_publish(&self, hash: impl Into<String>) -> Result<()> {
+        let hash = hash.into();
+        self.call("name/publish", &[("arg", hash.as_str())], None)
+            .await?;
+        Ok(())
+    }
+
+    /// Pins a CID recursively. The CID can be a [`Cid`] or its string form.
+    pub async fn pin_add(
+        &self,
+        cid: impl TryInto<Cid, Error = impl Into<Error>>,
+    ) -> Result<Vec<Cid>> {
+        let cid: Cid = cid.try_into().map_err(Into::into)?;
+        let cid = cid.to_string();
+        let query = [
+            ("arg", cid.as_str()),
+            ("recursive", "true"),
+            ("progress", "false"),
+        ];
+        let resp = self.call_json("pin/add", &query, None).await?;
+        crate::pin_add::parse_pins(&resp)
+    }
+
+    /// Shut down the IPFS daemon
+    pub async fn shutdown(&self) -> Result<()> {
+        self.call("shutdown", &[], None).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::add::AddOptions;
+    use crate::testing::{MockResponse, MockServer};
+    use crate::{Error, IpfsApi, Result};
+    use bytes::Bytes;
+    use futures_util::{StreamExt, TryStreamExt};
+    use serde_json::json;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_cat_stream() {
+        let server = MockServer::start();
+        server.on("cat", MockResponse::stream(["Hello ", "World\n"]));
+        let api = IpfsApi::builder()
+            .base_url(&server.base_url())
+            .build_async()
+            .unwrap();
+
+        let chunks: Vec<_> = api
+            .cat("QmHash")
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(chunks.concat(), b"Hello World\n");
+        assert_eq!(server.requests()[0].args(), ["QmHash"]);
+    }
+
+    #[tokio::test]
+    async fn test_cat_stream_error() {
+        let server = MockServer::start();
+        server.on(
+            "cat",
+            MockResponse::stream(["Hello "]).stream_error("deadline exceeded"),
+        );
+        let api = IpfsApi::builder()
+            .base_url(&server.base_url())
+            .build_async()
+            .unwrap();
+
+        let stream = api.cat("QmHash").await.unwrap();
+        let chunks: Vec<Result<Bytes>> = stream.collect().await;
+        assert_eq!(chunks[0].as_ref().unwrap().as_ref(), b"Hello ");
+        match chunks.last() {
+            Some(Err(Error::Stream(msg))) => {
+                assert_eq!(msg, "deadline exceeded")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_put() {
+        let server = MockServer::start();
+        let key = "bafkreide5semuafsnds3ugrvm6fbwuyw2ijpj43gwjdxemstjkfozi37hq";
+        server.on("block/put", MockResponse::json(&json!({"Key": key})));
+        server.on("pin/add", MockResponse::error("pin: not found"));
+        let api = IpfsApi::builder()
+            .base_url(&server.base_url())
+            .build_async()
+            .unwrap();
+
+        let cid = api.block_put(b"Hello world").await.unwrap();
+        assert_eq!(cid.to_string(), key);
+        match api.pin_add(&cid).await {
+            Err(Error::Api { message, .. }) => {
+                assert_eq!(message, "pin: not found")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let parts = server.requests()[0].parts().unwrap();
+        assert_eq!(parts[0].body, b"Hello world");
+    }
+
+    #[tokio::test]
+    async fn test_add_reader() {
+        let server = MockServer::start();
+        let hash = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u";
+        server.on(
+            "add",
+            MockResponse::ndjson([json!({
+                "Name": hash,
+                "Hash": hash,
+                "Size": "11",
+            })]),
+        );
+        let api = IpfsApi::builder()
+            .base_url(&server.base_url())
+            .build_async()
+            .unwrap();
+
+        let data = (&b"Hello "[..]).chain(&b"world"[..]);
+        let added = api.add(data, &AddOptions::new()).await.unwrap();
+        assert_eq!(added[0].hash.to_string(), hash);
+
+        let request = &server.requests()[0];
+        assert_eq!(request.header("Transfer-Encoding"), Some("chunked"));
+        let parts = request.parts().unwrap();
+        assert_eq!(parts[0].filename.as_deref(), Some(""));
+        assert_eq!(parts[0].body, b"Hello world");
+    }
+}
@@ -0,0 +1,76 @@
+use crate::multibase::Base;
+use std::fmt::Debug;
+
+/// Credentials for an API endpoint secured with Kubo's
+/// `API.Authorizations`, or by a reverse proxy.
+///
+/// The credentials are sent with every request, and are never included in
+/// `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Auth {
+    /// HTTP Basic authentication, matching a `basic:user:password` secret.
+    Basic { username: String, password: String },
+
+    /// Bearer token authentication, matching a `bearer:token` secret.
+    Bearer(String),
+}
+
+impl Auth {
+    /// Returns the value of the `Authorization` header.
+    pub(crate) fn header_value(&self) -> String {
+        match self {
+            Auth::Basic { username, password } => {
+                let credentials = format!("{}:{}", username, password);
+                format!(
+                    "Basic {}",
+                    Base::Base64Pad.encode(credentials.as_bytes())
+                )
+            }
+            Auth::Bearer(token) => format!("Bearer {}", token),
+        }
+    }
+}
+
+impl Debug for Auth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Auth::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+            Auth::Bearer(_) => {
+                f.debug_tuple("Bearer").field(&"<redacted>").finish()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Auth;
+    use crate::IpfsApi;
+
+    #[test]
+    fn test_header_value() {
+        let auth = Auth::Basic {
+            username: "Aladdin".into(),
+            password: "open sesame".into(),
+        };
+        assert_eq!(auth.header_value(), "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+
+        let auth = Auth::Bearer("secret-token".into());
+        assert_eq!(auth.header_value(), "Bearer secret-token");
+    }
+
+    #[test]
+    fn test_debug_redacted() {
+        let api = IpfsApi::builder().basic_auth("admin", "hunter2").build();
+        let debug = format!("{:?}", api);
+        assert!(debug.contains("admin"));
+        assert!(!debug.contains("hunter2"));
+
+        let api = IpfsApi::builder().bearer_auth("secret-token").build();
+        assert!(!format!("{:?}", api).contains("secret-token"));
+    }
+}
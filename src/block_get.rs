@@ -1,41 +1,164 @@
-use crate::IpfsApi;
+use crate::cid::Cid;
+use crate::{ByteStream, Error, IpfsApi, Result};
 use std::io::Read;
 
+/// The largest block accepted by [`IpfsApi::block_get_verified`]. Kubo
+/// refuses to store larger blocks.
+pub(crate) const MAX_BLOCK_SIZE: u64 = 4 * 1024 * 1024;
+
 impl IpfsApi {
-    /// Get a raw IPFS block.
+    /// Get a raw IPFS block. The CID can be a [`Cid`] or its string form.
     ///
     /// # Notes
     /// This is a wrapper for the `/api/v0/block/get` API.
     ///
     /// # Errors
-    /// This function can return an error if the IPFS node is down, or if the
-    /// block cannot be retrieved.
+    /// This function can return an error if the CID cannot be parsed, if the
+    /// IPFS node is down, or if the block cannot be retrieved.
     ///
     /// # Examples
     ///
-    /// ```rust
+    /// ```rust,no_run
     /// # use ipfsapi::IpfsApi;
+    /// use std::io::Read;
+    ///
     /// let api = IpfsApi::new("127.0.0.1", 5001);
     ///
     /// let hash = "QmV8cfu6n4NT5xRr2AHdKxFMTZEJrA44qgrBCr739BN9Wb";
-    /// let block = api.block_get(hash).unwrap();
-    /// let string = String::from_utf8(block.collect()).unwrap();
+    /// let mut block = api.block_get(hash).unwrap();
+    /// let mut string = String::new();
+    /// block.read_to_string(&mut string).unwrap();
     ///
     /// assert_eq!(string, "Hello world");
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
     pub fn block_get(
         &self,
-        hash: &str,
-    ) -> Result<impl Iterator<Item = u8>, ureq::Error> {
+        cid: impl TryInto<Cid, Error = impl Into<Error>>,
+    ) -> Result<ByteStream> {
         crate::rand::feed_event("block_get");
 
-        let url =
-            format!("http://{}:{}/api/v0/block/get", self.server, self.port);
-        let req = ureq::post(&url).query("arg", hash);
+        let cid: Cid = cid.try_into().map_err(Into::into)?;
+        let req = self.post("block/get").query("arg", &cid.to_string());
         let resp = req.call()?;
-        let bytes = resp.into_reader().bytes();
 
-        Ok(bytes.filter_map(std::result::Result::ok))
+        Ok(resp.into_stream())
+    }
+
+    /// Get a raw IPFS block, and check that it matches the multihash of its
+    /// CID. This makes it safe to read blocks from a node that is not
+    /// trusted.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/block/get` API.
+    ///
+    /// # Errors
+    /// Returns [`Error::Integrity`] if the block does not match the CID, and
+    /// [`Error::InvalidCid`] if the CID uses a hash function that is not
+    /// supported by [`Multihash::hash`](crate::cid::Multihash::hash).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// use ipfsapi::cid::Cid;
+    ///
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let cid: Cid =
+    ///     "bafkreide5semuafsnds3ugrvm6fbwuyw2ijpj43gwjdxemstjkfozi37hq".parse()?;
+    /// let block = api.block_get_verified(&cid)?;
+    ///
+    /// assert_eq!(block, b"Hello world");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn block_get_verified(
+        &self,
+        cid: impl TryInto<Cid, Error = impl Into<Error>>,
+    ) -> Result<Vec<u8>> {
+        let cid: Cid = cid.try_into().map_err(Into::into)?;
+
+        // Fail before the request if the block cannot be checked.
+        cid.hash().matches(b"")?;
+
+        let mut block = Vec::new();
+        self.block_get(&cid)?
+            .take(MAX_BLOCK_SIZE + 1)
+            .read_to_end(&mut block)?;
+
+        verify(&cid, block)
+    }
+}
+
+/// Checks that a block matches its CID.
+pub(crate) fn verify(cid: &Cid, block: Vec<u8>) -> Result<Vec<u8>> {
+    if block.len() as u64 > MAX_BLOCK_SIZE {
+        let msg =
+            format!("block {} is larger than {} bytes", cid, MAX_BLOCK_SIZE);
+        return Err(Error::Integrity(msg));
+    }
+    if !cid.hash().matches(&block)? {
+        let msg = format!("block does not match its CID {}", cid);
+        return Err(Error::Integrity(msg));
+    }
+
+    Ok(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cid::{self, Cid, Multihash};
+    use crate::testing::{MockResponse, MockServer};
+    use crate::Error;
+
+    #[test]
+    fn test_block_get_verified() {
+        let data = b"Hello world";
+        let server = MockServer::start();
+        server.on("block/get", MockResponse::new(200, "Hello world"));
+        let api = server.api();
+
+        for code in [
+            cid::IDENTITY,
+            cid::SHA2_256,
+            cid::SHA2_512,
+            cid::BLAKE2B_256,
+            cid::BLAKE3,
+        ] {
+            let hash = Multihash::hash(code, data).unwrap();
+            let cid = Cid::new_v1(cid::RAW, hash);
+            assert_eq!(api.block_get_verified(&cid).unwrap(), data);
+
+            let other = Multihash::hash(code, b"Hello World").unwrap();
+            let cid = Cid::new_v1(cid::RAW, other);
+            match api.block_get_verified(&cid) {
+                Err(Error::Integrity(_)) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        let unsupported = Cid::new_v1(cid::RAW, Multihash::new(0x14, [0; 64]));
+        let requests = server.requests().len();
+        assert!(api.block_get_verified(&unsupported).is_err());
+        assert_eq!(server.requests().len(), requests);
+    }
+
+    #[test]
+    fn test_block_get_string() {
+        let server = MockServer::start();
+        server.on("block/get", MockResponse::new(200, "Hello world"));
+        let api = server.api();
+
+        let hash = "QmV8cfu6n4NT5xRr2AHdKxFMTZEJrA44qgrBCr739BN9Wb";
+        api.block_get(hash).unwrap();
+        api.block_get(String::from(hash)).unwrap();
+        assert_eq!(server.requests()[0].args(), [hash]);
+        assert_eq!(server.requests()[1].args(), [hash]);
+
+        match api.block_get("not a cid") {
+            Err(Error::InvalidMultibase(_)) => {}
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        assert_eq!(server.requests().len(), 2);
     }
 }
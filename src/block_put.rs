@@ -1,7 +1,7 @@
-use crate::IpfsApi;
-use std::error::Error;
-use std::io::Cursor;
-use std::io::Write;
+use crate::cid::Cid;
+use crate::multipart::{Multipart, Part};
+use crate::{Error, IpfsApi, Result};
+use std::io::Read;
 
 impl IpfsApi {
     /// Puts a raw block into IPFS.
@@ -9,49 +9,68 @@ impl IpfsApi {
     /// # Notes
     /// This is a wrapper for the `/api/v0/block/put` API.
     ///
-    pub fn block_put(&self, data: &[u8]) -> Result<String, Box<dyn Error>> {
+    pub fn block_put(&self, data: &[u8]) -> Result<Cid> {
+        self.block_put_part(Part::bytes("data", data))
+    }
+
+    /// Puts a raw block into IPFS, streaming its contents from a reader.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/block/put` API.
+    ///
+    pub fn block_put_reader(&self, data: impl Read + Send) -> Result<Cid> {
+        self.block_put_part(Part::new("data", data))
+    }
+
+    fn block_put_part(&self, part: Part<'_>) -> Result<Cid> {
         crate::rand::feed_event("block_put");
-        let endpoint = "api/v0/block/put";
-        let url = format!("http://{}:{}/{}", self.server, self.port, endpoint);
-
-        let boundary = {
-            let mut s = String::with_capacity(32);
-            for _ in 0..32 {
-                s.push(crate::rand::hex_digit());
-            }
-            s
-        };
-
-        let mut buffer: Vec<u8> = Vec::new();
-        write!(buffer, "--{}\r\n", boundary)?;
-        write!(buffer, "Content-Disposition: form-data; name=\"data\"\r\n")?;
-        write!(buffer, "\r\n")?;
-        buffer.extend(data);
-        write!(buffer, "\r\n--{}--\r\n", boundary)?;
-
-        let resp = ureq::post(&url)
-            .set(
-                "Content-Type",
-                &format!("multipart/form-data; boundary=\"{}\"", boundary),
-            )
-            .send(Cursor::new(buffer))?;
+
+        let mut body = Multipart::new();
+        body.add_part(part);
+
+        let resp = self
+            .post("block/put")
+            .set("Content-Type", &body.content_type())
+            .send(body.into_reader())?;
 
         let resp = resp.into_reader();
         let resp: serde_json::Value = serde_json::from_reader(resp)?;
 
-        Ok(resp["Key"].as_str().ok_or("Key not a string")?.into())
+        parse_key(&resp)
+    }
+}
+
+pub(crate) fn parse_key(resp: &serde_json::Value) -> Result<Cid> {
+    match resp["Key"].as_str() {
+        Some(key) => key.parse(),
+        None => Err(Error::response("Key not a string")),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::IpfsApi;
+    use crate::testing::{MockResponse, MockServer};
+    use serde_json::json;
+    use std::io::Read;
 
     #[test]
     fn test_block_put() {
-        let api = IpfsApi::new("127.0.0.1", 5001);
+        let key = "bafkreide5semuafsnds3ugrvm6fbwuyw2ijpj43gwjdxemstjkfozi37hq";
+        let server = MockServer::start();
+        server.on("block/put", MockResponse::json(&json!({"Key": key})));
+        server.on("block/get", MockResponse::new(200, "Hello world"));
+        let api = server.api();
+
         let hash = api.block_put(b"Hello world").unwrap();
-        let got: Vec<u8> = api.block_get(&hash).unwrap().collect();
+        let mut got = Vec::new();
+        api.block_get(&hash).unwrap().read_to_end(&mut got).unwrap();
         assert_eq!(got, b"Hello world");
+
+        let requests = server.requests();
+        let parts = requests[0].parts().unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].name, "data");
+        assert_eq!(parts[0].body, b"Hello world");
+        assert_eq!(requests[1].args(), [key]);
     }
 }
@@ -0,0 +1,344 @@
+use crate::multiaddr::ApiAddr;
+use crate::transport::{HttpTransport, Transport};
+use crate::{Auth, IpfsApi, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The default location of the API of a local IPFS daemon.
+pub const DEFAULT_BASE_URL: &str = "http://127.0.0.1:5001/api/v0";
+
+/// Configures and creates an [`IpfsApi`].
+///
+/// ```rust
+/// # use ipfsapi::IpfsApi;
+/// # use std::time::Duration;
+/// let api = IpfsApi::builder()
+///     .base_url("https://example.com/ipfs/api/v0")
+///     .timeout_connect(Duration::from_secs(5))
+///     .user_agent("my-app/1.0")
+///     .header("X-Request-Source", "my-app")
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct IpfsApiBuilder {
+    base_url: String,
+    timeout_connect: Option<Duration>,
+    timeout_read: Option<Duration>,
+    user_agent: Option<String>,
+    headers: Vec<(String, String)>,
+    auth: Option<Auth>,
+    unix_socket: Option<PathBuf>,
+    transport: Option<Arc<dyn Transport>>,
+}
+
+impl Default for IpfsApiBuilder {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.into(),
+            timeout_connect: None,
+            timeout_read: None,
+            user_agent: None,
+            headers: Vec::new(),
+            auth: None,
+            unix_socket: None,
+            transport: None,
+        }
+    }
+}
+
+impl IpfsApiBuilder {
+    /// Sets the URL that API endpoints are relative to, including the API
+    /// path prefix. Defaults to [`DEFAULT_BASE_URL`].
+    ///
+    /// A daemon behind a reverse proxy can be reached with something like
+    /// `https://example.com/ipfs/api/v0`.
+    #[must_use]
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').into();
+        self
+    }
+
+    /// Sets the API address from a multiaddr, such as
+    /// `/ip4/127.0.0.1/tcp/5001` or `/unix/run/ipfs.sock`.
+    ///
+    /// # Errors
+    /// Returns an error if the multiaddr cannot be parsed, or if it is a Unix
+    /// socket address on a platform without Unix sockets.
+    pub fn multiaddr(mut self, addr: &str) -> Result<Self> {
+        match crate::multiaddr::parse(addr)? {
+            ApiAddr::Http(base_url) => {
+                self.base_url = base_url;
+                self.unix_socket = None;
+            }
+            #[cfg(unix)]
+            ApiAddr::Unix(path) => {
+                self.base_url = "http://localhost/api/v0".into();
+                self.unix_socket = Some(path);
+            }
+            #[cfg(not(unix))]
+            ApiAddr::Unix(_) => {
+                return Err(crate::Error::InvalidMultiaddr(format!(
+                    "{}: Unix sockets are not supported on this platform",
+                    addr
+                )));
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Sets the API address from the multiaddr the daemon advertises in
+    /// `$IPFS_PATH/api`, falling back to `~/.ipfs/api`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, which usually means the
+    /// daemon is not running, or if its contents cannot be parsed.
+    pub fn from_env(self) -> Result<Self> {
+        self.multiaddr(&crate::multiaddr::read_api_file()?)
+    }
+
+    /// Sends requests over the Unix domain socket at `path` instead of TCP.
+    /// The path prefix of the base URL is still used for the endpoints.
+    #[cfg(unix)]
+    #[must_use]
+    pub fn unix_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.unix_socket = Some(path.into());
+        self
+    }
+
+    /// Sets the timeout for connecting to the daemon.
+    ///
+    /// The blocking client only applies it to TCP connections, as connecting
+    /// to a [Unix socket](Self::unix_socket) fails or succeeds immediately.
+    #[must_use]
+    pub fn timeout_connect(mut self, timeout: Duration) -> Self {
+        self.timeout_connect = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout for individual reads of the response. Streaming
+    /// responses can take longer than this in total.
+    #[must_use]
+    pub fn timeout_read(mut self, timeout: Duration) -> Self {
+        self.timeout_read = Some(timeout);
+        self
+    }
+
+    /// Sets the `User-Agent` header sent with every request.
+    #[must_use]
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Adds a header that is sent with every request.
+    #[must_use]
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the credentials that are sent with every request.
+    #[must_use]
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Authenticates every request with HTTP Basic authentication.
+    #[must_use]
+    pub fn basic_auth(self, username: &str, password: &str) -> Self {
+        self.auth(Auth::Basic {
+            username: username.into(),
+            password: password.into(),
+        })
+    }
+
+    /// Authenticates every request with a bearer token.
+    #[must_use]
+    pub fn bearer_auth(self, token: &str) -> Self {
+        self.auth(Auth::Bearer(token.into()))
+    }
+
+    /// Sends requests through a custom [`Transport`] instead of HTTP. The
+    /// address, timeout and user agent settings are not used by custom
+    /// transports.
+    #[must_use]
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Creates the [`IpfsApi`].
+    #[must_use]
+    pub fn build(self) -> IpfsApi {
+        let transport = match &self.transport {
+            Some(transport) => transport.clone(),
+            None => self.default_transport(),
+        };
+
+        IpfsApi {
+            transport,
+            headers: self.headers,
+            auth: self.auth,
+        }
+    }
+
+    fn default_transport(&self) -> Arc<dyn Transport> {
+        #[cfg(unix)]
+        if let Some(path) = &self.unix_socket {
+            return Arc::new(crate::unix::UnixTransport::new(
+                path.clone(),
+                &self.base_url,
+                self.timeout_read,
+                self.user_agent.clone(),
+            ));
+        }
+
+        let mut agent = ureq::AgentBuilder::new();
+
+        if let Some(timeout) = self.timeout_connect {
+            agent = agent.timeout_connect(timeout);
+        }
+
+        if let Some(timeout) = self.timeout_read {
+            agent = agent.timeout_read(timeout);
+        }
+
+        if let Some(user_agent) = &self.user_agent {
+            agent = agent.user_agent(user_agent);
+        }
+
+        Arc::new(HttpTransport::with_agent(&self.base_url, agent.build()))
+    }
+
+    /// Creates an [`AsyncIpfsApi`](crate::AsyncIpfsApi) with the same
+    /// settings.
+    ///
+    /// # Errors
+    /// Returns an error if the HTTP client cannot be initialized.
+    #[cfg(feature = "async")]
+    pub fn build_async(self) -> Result<crate::AsyncIpfsApi> {
+        let mut client = reqwest::Client::builder();
+
+        if let Some(timeout) = self.timeout_connect {
+            client = client.connect_timeout(timeout);
+        }
+
+        if let Some(timeout) = self.timeout_read {
+            client = client.read_timeout(timeout);
+        }
+
+        if let Some(user_agent) = &self.user_agent {
+            client = client.user_agent(user_agent);
+        }
+
+        #[cfg(unix)]
+        if let Some(path) = self.unix_socket {
+            client = client.unix_socket(path);
+        }
+
+        Ok(crate::AsyncIpfsApi::from_parts(
+            self.base_url,
+            client.build()?,
+            self.headers,
+            self.auth,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{MockResponse, MockServer};
+    use crate::{Error, IpfsApi};
+    use serde_json::json;
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    /// Serializes the tests that change the environment.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn version() -> MockResponse {
+        MockResponse::json(&json!({"Version": "0.30.0", "Commit": ""}))
+    }
+
+    #[test]
+    fn test_headers() {
+        let server = MockServer::start();
+        server.on("version", version());
+
+        let api = IpfsApi::builder()
+            .base_url(&server.base_url())
+            .timeout_connect(Duration::from_secs(5))
+            .timeout_read(Duration::from_secs(5))
+            .user_agent("my-app/1.0")
+            .header("X-Request-Source", "my-app")
+            .build();
+        api.version().unwrap();
+
+        let request = &server.requests()[0];
+        assert_eq!(request.header("User-Agent"), Some("my-app/1.0"));
+        assert_eq!(request.header("X-Request-Source"), Some("my-app"));
+    }
+
+    #[test]
+    fn test_base_url_prefix() {
+        let server = MockServer::start();
+        server.on("version", version());
+
+        // The trailing slash is trimmed.
+        let base_url = server.base_url().replace("/api/v0", "/ipfs/api/v0/");
+        let api = IpfsApi::builder().base_url(&base_url).build();
+        api.version().unwrap();
+
+        let request = &server.requests()[0];
+        assert_eq!(request.path, "/ipfs/api/v0/version");
+        assert_eq!(request.endpoint, "version");
+    }
+
+    #[test]
+    fn test_timeout_read() {
+        // The connection is accepted by the kernel, but nothing replies.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let api = IpfsApi::builder()
+            .base_url(&format!("http://{}/api/v0", addr))
+            .timeout_read(Duration::from_millis(100))
+            .build();
+
+        let start = Instant::now();
+        assert!(matches!(api.version(), Err(Error::Transport(_))));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_from_env() {
+        let _guard = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = std::env::temp_dir()
+            .join(format!("ipfsapi-env-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("api"), "/ip4/127.0.0.1/tcp/5002\n").unwrap();
+
+        let previous = std::env::var_os("IPFS_PATH");
+        std::env::set_var("IPFS_PATH", &dir);
+        let found = IpfsApi::builder().from_env();
+        std::fs::remove_file(dir.join("api")).unwrap();
+        let missing = IpfsApi::builder().from_env();
+        match previous {
+            Some(path) => std::env::set_var("IPFS_PATH", path),
+            None => std::env::remove_var("IPFS_PATH"),
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(found.unwrap().base_url, "http://127.0.0.1:5002/api/v0");
+        match missing {
+            Err(Error::Io(e)) => {
+                assert_eq!(e.kind(), std::io::ErrorKind::NotFound)
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
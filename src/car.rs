@@ -0,0 +1,729 @@
+//! Reading and writing CAR (Content Addressable aRchive) files, which hold
+//! the blocks of one or more DAGs. This works without a daemon, and the
+//! files can be exchanged with [`IpfsApi::dag_export`] and
+//! [`IpfsApi::dag_import`](crate::IpfsApi::dag_import).
+//!
+//! Both CARv1 files and CARv2 files, which wrap a CARv1 payload and add an
+//! index of the blocks, are supported.
+//!
+//! ```rust
+//! use ipfsapi::car::{CarReader, CarWriter};
+//! use ipfsapi::cid::{Cid, Multihash};
+//!
+//! // The CID is not checked against the data when writing or reading.
+//! let cid = Cid::new_v1(0x55, Multihash::new(0x12, [0; 32]));
+//!
+//! let mut writer = CarWriter::new_v2(Vec::new(), &[cid.clone()])?;
+//! writer.write_block(&cid, b"Hello world")?;
+//! let car = writer.finish()?;
+//!
+//! let mut reader = CarReader::new(&car[..])?;
+//! assert_eq!(reader.roots(), [cid.clone()]);
+//! assert_eq!(reader.next().unwrap()?, (cid, b"Hello world".to_vec()));
+//! # Ok::<(), ipfsapi::Error>(())
+//! ```
+//!
+//! [`IpfsApi::dag_export`]: crate::IpfsApi::dag_export
+
+use crate::cid::{Cid, Multihash};
+use crate::varint;
+use crate::{Error, Result};
+use std::io::{Read, Take, Write};
+
+/// The start of every CARv2 file: a CARv1 header of `{"version": 2}`.
+const V2_PRAGMA: [u8; 11] = [
+    0x0a, 0xa1, 0x67, b'v', b'e', b'r', b's', b'i', b'o', b'n', 0x02,
+];
+
+/// The length of the header that follows the CARv2 pragma.
+const V2_HEADER_LEN: usize = 40;
+
+/// The offset of the CARv1 payload written in CARv2 files.
+const V2_DATA_OFFSET: u64 = (V2_PRAGMA.len() + V2_HEADER_LEN) as u64;
+
+/// The largest CARv1 header that is read, as in go-car.
+const MAX_HEADER_SIZE: u64 = 32 << 20;
+
+/// The largest block section that is read, as in go-car.
+const MAX_SECTION_SIZE: u64 = 8 << 20;
+
+/// The multicodec code of the `MultihashIndexSorted` CARv2 index.
+const MULTIHASH_INDEX_SORTED: u64 = 0x0401;
+
+/// The CBOR tag of IPLD links.
+const CID_TAG: u64 = 42;
+
+fn invalid(msg: &str) -> Error {
+    Error::InvalidCar(msg.into())
+}
+
+/// Reads exactly `buf.len()` bytes, treating an early end as a truncated
+/// file.
+fn read_exact(reader: &mut impl Read, buf: &mut [u8]) -> Result<()> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => invalid("unexpected end of file"),
+        _ => e.into(),
+    })
+}
+
+/// Skips `len` bytes of a reader.
+fn skip(reader: &mut impl Read, len: u64) -> Result<()> {
+    let skipped = std::io::copy(&mut reader.take(len), &mut std::io::sink())?;
+    if skipped != len {
+        return Err(invalid("unexpected end of file"));
+    }
+
+    Ok(())
+}
+
+fn read_u64(buf: &[u8]) -> u64 {
+    u64::from_le_bytes(buf[..8].try_into().unwrap())
+}
+
+/// A decoder for the subset of DAG-CBOR used by CAR headers.
+struct Cbor<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Cbor<'a> {
+    /// Reads the major type and argument of the next item.
+    fn head(&mut self) -> Result<(u8, u64)> {
+        let (&first, rest) = self
+            .buf
+            .split_first()
+            .ok_or_else(|| invalid("truncated header"))?;
+
+        let len = match first & 0x1f {
+            info @ 0..=23 => {
+                self.buf = rest;
+                return Ok((first >> 5, u64::from(info)));
+            }
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return Err(invalid("unsupported CBOR item in the header")),
+        };
+
+        if rest.len() < len {
+            return Err(invalid("truncated header"));
+        }
+        let arg = rest[..len]
+            .iter()
+            .fold(0, |arg, &byte| arg << 8 | u64::from(byte));
+        self.buf = &rest[len..];
+        Ok((first >> 5, arg))
+    }
+
+    /// Reads the next `len` bytes.
+    fn bytes(&mut self, len: u64) -> Result<&'a [u8]> {
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|&len| len <= self.buf.len())
+            .ok_or_else(|| invalid("truncated header"))?;
+
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    /// Skips the next item, which is nested at most `depth` times.
+    fn skip(&mut self, depth: usize) -> Result<()> {
+        if depth == 0 {
+            return Err(invalid("header is nested too deeply"));
+        }
+
+        let (major, arg) = self.head()?;
+        match major {
+            2 | 3 => {
+                self.bytes(arg)?;
+            }
+            4 | 5 => {
+                let items = if major == 5 {
+                    arg.saturating_mul(2)
+                } else {
+                    arg
+                };
+                for _ in 0..items {
+                    self.skip(depth - 1)?;
+                }
+            }
+            6 => self.skip(depth - 1)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Reads an IPLD link.
+    fn link(&mut self) -> Result<Cid> {
+        let link = match (self.head()?, self.head()?) {
+            ((6, CID_TAG), (2, len)) => self.bytes(len)?,
+            _ => return Err(invalid("root is not a CID")),
+        };
+
+        // Links start with the multibase prefix of raw binary.
+        match link.split_first() {
+            Some((0, cid)) => Cid::from_bytes(cid)
+                .map_err(|e| invalid(&format!("invalid root: {}", e))),
+            _ => Err(invalid("root is not a binary CID")),
+        }
+    }
+}
+
+/// Decodes a CARv1 header, or the pragma of a CARv2 file, and returns its
+/// version and roots.
+fn parse_header(buf: &[u8]) -> Result<(u64, Option<Vec<Cid>>)> {
+    let mut cbor = Cbor { buf };
+    let fields = match cbor.head()? {
+        (5, fields) => fields,
+        _ => return Err(invalid("header is not a map")),
+    };
+
+    let mut version = None;
+    let mut roots = None;
+    for _ in 0..fields {
+        let key = match cbor.head()? {
+            (3, len) => cbor.bytes(len)?,
+            _ => return Err(invalid("header key is not a string")),
+        };
+
+        match key {
+            b"version" => match cbor.head()? {
+                (0, v) => version = Some(v),
+                _ => return Err(invalid("version is not an integer")),
+            },
+            b"roots" => {
+                let count = match cbor.head()? {
+                    (4, count) => count,
+                    _ => return Err(invalid("roots is not an array")),
+                };
+                let links = (0..count).map(|_| cbor.link());
+                roots = Some(links.collect::<Result<_>>()?);
+            }
+            _ => cbor.skip(16)?,
+        }
+    }
+
+    if !cbor.buf.is_empty() {
+        return Err(invalid("trailing bytes after the header"));
+    }
+
+    let version = version.ok_or_else(|| invalid("header has no version"))?;
+    Ok((version, roots))
+}
+
+/// Encodes the CARv1 header of a file with the given roots.
+fn encode_header(roots: &[Cid]) -> Vec<u8> {
+    fn head(out: &mut Vec<u8>, major: u8, arg: u64) {
+        let major = major << 5;
+        match arg {
+            0..=23 => out.push(major | arg as u8),
+            24..=0xff => out.extend([major | 24, arg as u8]),
+            0x100..=0xffff => {
+                out.push(major | 25);
+                out.extend((arg as u16).to_be_bytes());
+            }
+            0x1_0000..=0xffff_ffff => {
+                out.push(major | 26);
+                out.extend((arg as u32).to_be_bytes());
+            }
+            _ => {
+                out.push(major | 27);
+                out.extend(arg.to_be_bytes());
+            }
+        }
+    }
+
+    // DAG-CBOR sorts map keys by length first.
+    let mut header = Vec::new();
+    head(&mut header, 5, 2);
+    head(&mut header, 3, 5);
+    header.extend(b"roots");
+    head(&mut header, 4, roots.len() as u64);
+    for root in roots {
+        let cid = root.to_bytes();
+        head(&mut header, 6, CID_TAG);
+        head(&mut header, 2, cid.len() as u64 + 1);
+        header.push(0);
+        header.extend(cid);
+    }
+    head(&mut header, 3, 7);
+    header.extend(b"version");
+    head(&mut header, 0, 1);
+
+    let mut out = Vec::with_capacity(header.len() + 2);
+    varint::encode(header.len() as u64, &mut out);
+    out.extend(header);
+    out
+}
+
+/// Reads a varint-prefixed CARv1 header.
+fn read_header(reader: &mut impl Read) -> Result<Vec<u8>> {
+    let len =
+        varint::read(reader)?.ok_or_else(|| invalid("the file is empty"))?;
+    if len == 0 || len > MAX_HEADER_SIZE {
+        let msg = format!("invalid header length {}", len);
+        return Err(invalid(&msg));
+    }
+
+    let mut header = vec![0; len as usize];
+    read_exact(reader, &mut header)?;
+    Ok(header)
+}
+
+/// The header of a CAR file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CarHeader {
+    /// The version of the file, 1 or 2.
+    pub version: u64,
+
+    /// The roots of the DAGs in the file.
+    pub roots: Vec<Cid>,
+}
+
+/// The location of the CARv1 payload and of the index of a CARv2 file.
+#[derive(Clone, Copy, Debug)]
+struct V2Header {
+    data_offset: u64,
+    data_size: u64,
+    index_offset: u64,
+}
+
+/// An entry of the index of a CARv2 file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The multihash of the block.
+    pub hash: Multihash,
+
+    /// The offset of the block's section, from the start of the CARv1
+    /// payload.
+    pub offset: u64,
+}
+
+/// A reader of CARv1 and CARv2 files, which is an iterator over the blocks
+/// of the file. The iterator ends after the first error.
+///
+/// The header is validated when the reader is created. The CIDs of the
+/// blocks are decoded, but not checked against their data.
+#[derive(Debug)]
+pub struct CarReader<R> {
+    reader: Take<R>,
+    header: CarHeader,
+    v2: Option<V2Header>,
+    failed: bool,
+}
+
+impl<R: Read> CarReader<R> {
+    /// Reads the header of a CAR file.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let header = read_header(&mut reader)?;
+        let (version, roots) = parse_header(&header)?;
+
+        match version {
+            1 => {
+                let roots =
+                    roots.ok_or_else(|| invalid("header has no roots"))?;
+                Ok(Self {
+                    reader: reader.take(u64::MAX),
+                    header: CarHeader { version, roots },
+                    v2: None,
+                    failed: false,
+                })
+            }
+            2 => {
+                if header != V2_PRAGMA[1..] {
+                    return Err(invalid("invalid CARv2 pragma"));
+                }
+
+                let mut buf = [0; V2_HEADER_LEN];
+                read_exact(&mut reader, &mut buf)?;
+                let v2 = V2Header {
+                    data_offset: read_u64(&buf[16..]),
+                    data_size: read_u64(&buf[24..]),
+                    index_offset: read_u64(&buf[32..]),
+                };
+                if v2.data_offset < V2_DATA_OFFSET {
+                    return Err(invalid("data offset overlaps the header"));
+                }
+
+                skip(&mut reader, v2.data_offset - V2_DATA_OFFSET)?;
+                let mut reader = reader.take(v2.data_size);
+                let (inner, roots) = parse_header(&read_header(&mut reader)?)?;
+                let roots = match (inner, roots) {
+                    (1, Some(roots)) => roots,
+                    _ => return Err(invalid("invalid CARv2 payload header")),
+                };
+
+                Ok(Self {
+                    reader,
+                    header: CarHeader { version, roots },
+                    v2: Some(v2),
+                    failed: false,
+                })
+            }
+            _ => {
+                let msg = format!("unsupported version {}", version);
+                Err(invalid(&msg))
+            }
+        }
+    }
+
+    /// Returns the header of the file.
+    pub fn header(&self) -> &CarHeader {
+        &self.header
+    }
+
+    /// Returns the roots of the DAGs in the file.
+    pub fn roots(&self) -> &[Cid] {
+        &self.header.roots
+    }
+
+    fn next_block(&mut self) -> Result<Option<(Cid, Vec<u8>)>> {
+        let len = match varint::read(&mut self.reader)? {
+            Some(len) => len,
+            None if self.reader.limit() > 0 && self.v2.is_some() => {
+                return Err(invalid("unexpected end of file"));
+            }
+            None => return Ok(None),
+        };
+        if len == 0 || len > MAX_SECTION_SIZE {
+            let msg = format!("invalid section length {}", len);
+            return Err(invalid(&msg));
+        }
+
+        let mut section = vec![0; len as usize];
+        read_exact(&mut self.reader, &mut section)?;
+        let (cid, cid_len) = Cid::read_bytes(&section)
+            .map_err(|e| invalid(&format!("invalid block CID: {}", e)))?;
+        let data = section.split_off(cid_len);
+
+        Ok(Some((cid, data)))
+    }
+
+    /// Skips the remaining blocks and reads the index of a CARv2 file.
+    /// Returns `None` for CARv1 files and CARv2 files without an index.
+    ///
+    /// Only the `MultihashIndexSorted` index, which Kubo and go-car write,
+    /// is supported.
+    pub fn into_index(mut self) -> Result<Option<Vec<IndexEntry>>> {
+        let v2 = match self.v2 {
+            Some(v2) => v2,
+            None => return Ok(None),
+        };
+
+        std::io::copy(&mut self.reader, &mut std::io::sink())?;
+        if self.reader.limit() > 0 {
+            return Err(invalid("unexpected end of file"));
+        }
+        if v2.index_offset == 0 {
+            return Ok(None);
+        }
+
+        let data_end = v2.data_offset.saturating_add(v2.data_size);
+        if v2.index_offset < data_end {
+            return Err(invalid("index offset overlaps the data"));
+        }
+
+        let mut reader = self.reader.into_inner();
+        skip(&mut reader, v2.index_offset - data_end)?;
+        read_index(&mut reader).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for CarReader<R> {
+    type Item = Result<(Cid, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let block = self.next_block();
+        self.failed = block.is_err();
+        block.transpose()
+    }
+}
+
+/// Reads a `MultihashIndexSorted` index.
+fn read_index(reader: &mut impl Read) -> Result<Vec<IndexEntry>> {
+    fn count(reader: &mut impl Read) -> Result<u32> {
+        let mut buf = [0; 4];
+        read_exact(reader, &mut buf)?;
+        u32::try_from(i32::from_le_bytes(buf))
+            .map_err(|_| invalid("negative index count"))
+    }
+
+    fn number(reader: &mut impl Read) -> Result<u64> {
+        let mut buf = [0; 8];
+        read_exact(reader, &mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    match varint::read(reader)? {
+        Some(MULTIHASH_INDEX_SORTED) => {}
+        Some(codec) => {
+            let msg = format!("unsupported index codec {:#x}", codec);
+            return Err(invalid(&msg));
+        }
+        None => return Err(invalid("unexpected end of file")),
+    }
+
+    let mut entries = Vec::new();
+    for _ in 0..count(reader)? {
+        let code = number(reader)?;
+        for _ in 0..count(reader)? {
+            let width = u64::from(count(reader)?);
+            let len = number(reader)?;
+            if width <= 8 || len % width != 0 {
+                return Err(invalid("invalid index bucket"));
+            }
+
+            let mut entry = vec![0; width as usize];
+            for _ in 0..len / width {
+                read_exact(reader, &mut entry)?;
+                let (digest, offset) = entry.split_at(entry.len() - 8);
+                entries.push(IndexEntry {
+                    hash: Multihash::new(code, digest),
+                    offset: read_u64(offset),
+                });
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Writes the `MultihashIndexSorted` index of the given entries, which
+/// must be sorted by their multihash.
+fn write_index(out: &mut impl Write, entries: &[IndexEntry]) -> Result<()> {
+    // The entries are grouped by hash function, then by digest length.
+    let group = |entries: &[IndexEntry], key: fn(&IndexEntry) -> u64| {
+        let mut groups: Vec<(u64, Vec<IndexEntry>)> = Vec::new();
+        for entry in entries {
+            match groups.iter_mut().find(|(k, _)| *k == key(entry)) {
+                Some((_, group)) => group.push(entry.clone()),
+                None => groups.push((key(entry), vec![entry.clone()])),
+            }
+        }
+        groups.sort_by_key(|(k, _)| *k);
+        groups
+    };
+
+    let mut index = Vec::new();
+    varint::encode(MULTIHASH_INDEX_SORTED, &mut index);
+    let codes = group(entries, |entry| entry.hash.code());
+    index.extend((codes.len() as i32).to_le_bytes());
+    for (code, entries) in codes {
+        index.extend(code.to_le_bytes());
+
+        let widths = group(&entries, |entry| entry.hash.digest().len() as u64);
+        index.extend((widths.len() as i32).to_le_bytes());
+        for (len, entries) in widths {
+            let width = len + 8;
+            index.extend((width as u32).to_le_bytes());
+            index.extend((width * entries.len() as u64).to_le_bytes());
+            for entry in entries {
+                index.extend(entry.hash.digest());
+                index.extend(entry.offset.to_le_bytes());
+            }
+        }
+    }
+
+    out.write_all(&index)?;
+    Ok(())
+}
+
+/// A writer of CARv1 and CARv2 files.
+///
+/// A CARv2 file starts with the size of its blocks, so its blocks are kept
+/// in memory until [`finish`](Self::finish) is called. CARv1 files are
+/// streamed to the writer.
+#[derive(Debug)]
+pub struct CarWriter<W: Write> {
+    writer: W,
+    payload: Option<Vec<u8>>,
+    index: Vec<IndexEntry>,
+}
+
+impl<W: Write> CarWriter<W> {
+    /// Starts a CARv1 file with the given roots.
+    pub fn new(mut writer: W, roots: &[Cid]) -> Result<Self> {
+        writer.write_all(&encode_header(roots))?;
+
+        Ok(Self {
+            writer,
+            payload: None,
+            index: Vec::new(),
+        })
+    }
+
+    /// Starts a CARv2 file with the given roots, and an index of its
+    /// blocks.
+    pub fn new_v2(writer: W, roots: &[Cid]) -> Result<Self> {
+        Ok(Self {
+            writer,
+            payload: Some(encode_header(roots)),
+            index: Vec::new(),
+        })
+    }
+
+    /// Writes a block. The CID is not checked against the data.
+    pub fn write_block(&mut self, cid: &Cid, data: &[u8]) -> Result<()> {
+        let cid_bytes = cid.to_bytes();
+        let mut section = Vec::with_capacity(cid_bytes.len() + data.len() + 4);
+        varint::encode((cid_bytes.len() + data.len()) as u64, &mut section);
+        section.extend(cid_bytes);
+        section.extend_from_slice(data);
+
+        match &mut self.payload {
+            Some(payload) => {
+                self.index.push(IndexEntry {
+                    hash: cid.hash().clone(),
+                    offset: payload.len() as u64,
+                });
+                payload.extend(section);
+            }
+            None => self.writer.write_all(&section)?,
+        }
+
+        Ok(())
+    }
+
+    /// Finishes the file and returns the writer.
+    pub fn finish(mut self) -> Result<W> {
+        if let Some(payload) = self.payload.take() {
+            // Blocks that were written twice are indexed once.
+            self.index.sort_by(|a, b| a.hash.cmp(&b.hash));
+            self.index.dedup_by(|a, b| a.hash == b.hash);
+
+            let data_size = payload.len() as u64;
+            let mut header = [0; V2_HEADER_LEN];
+            header[16..24].copy_from_slice(&V2_DATA_OFFSET.to_le_bytes());
+            header[24..32].copy_from_slice(&data_size.to_le_bytes());
+            let index_offset = V2_DATA_OFFSET + data_size;
+            header[32..].copy_from_slice(&index_offset.to_le_bytes());
+
+            self.writer.write_all(&V2_PRAGMA)?;
+            self.writer.write_all(&header)?;
+            self.writer.write_all(&payload)?;
+            write_index(&mut self.writer, &self.index)?;
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CarReader, CarWriter, V2_PRAGMA};
+    use crate::cid::{Cid, Multihash};
+    use crate::Error;
+
+    fn cid(byte: u8) -> Cid {
+        Cid::new_v1(0x55, Multihash::new(0x12, [byte; 32]))
+    }
+
+    fn blocks() -> Vec<(Cid, Vec<u8>)> {
+        vec![
+            (cid(2), b"second".to_vec()),
+            (cid(1), b"first".to_vec()),
+            (
+                Cid::new_v0(Multihash::new(0x12, [3; 32])).unwrap(),
+                vec![3; 300],
+            ),
+        ]
+    }
+
+    fn write(v2: bool) -> Vec<u8> {
+        let mut writer = match v2 {
+            true => CarWriter::new_v2(Vec::new(), &[cid(2)]).unwrap(),
+            false => CarWriter::new(Vec::new(), &[cid(2)]).unwrap(),
+        };
+        for (cid, data) in blocks() {
+            writer.write_block(&cid, &data).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_v1() {
+        let car = write(false);
+
+        // {"roots": [cid], "version": 1}
+        let mut header = vec![0x3a, 0xa2, 0x65];
+        header.extend(b"roots");
+        header.extend([0x81, 0xd8, 0x2a, 0x58, 0x25, 0x00]);
+        header.extend(cid(2).to_bytes());
+        header.push(0x67);
+        header.extend(b"version");
+        header.push(0x01);
+        assert_eq!(car[..header.len()], header);
+
+        let mut reader = CarReader::new(&car[..]).unwrap();
+        assert_eq!(reader.header().version, 1);
+        assert_eq!(reader.roots(), [cid(2)]);
+        let read: Vec<_> = reader.by_ref().map(Result::unwrap).collect();
+        assert_eq!(read, blocks());
+        assert_eq!(reader.into_index().unwrap(), None);
+    }
+
+    #[test]
+    fn test_v2() {
+        let car = write(true);
+        assert_eq!(car[..11], V2_PRAGMA);
+
+        let mut reader = CarReader::new(&car[..]).unwrap();
+        assert_eq!(reader.header().version, 2);
+        assert_eq!(reader.roots(), [cid(2)]);
+        assert_eq!(reader.next().unwrap().unwrap(), blocks()[0]);
+
+        let index = reader.into_index().unwrap().unwrap();
+        let hashes: Vec<_> = index.iter().map(|e| e.hash.digest()[0]).collect();
+        assert_eq!(hashes, [1, 2, 3]);
+
+        // The offsets point to the sections in the CARv1 payload.
+        let payload = &car[51..];
+        for entry in &index {
+            let section = &payload[entry.offset as usize..];
+            let (_, len) = crate::varint::decode(section).unwrap();
+            let (cid, _) = Cid::read_bytes(&section[len..]).unwrap();
+            assert_eq!(*cid.hash(), entry.hash);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let v1 = write(false);
+        let v2 = write(true);
+
+        // The version is the last byte of the header.
+        let mut unknown_version = v1.clone();
+        unknown_version[58] = 0x03;
+
+        let mut bad_offset = v2.clone();
+        bad_offset[27] = 10;
+
+        for car in [
+            &b""[..],
+            &[0x01, 0x80],
+            &unknown_version,
+            &v1[..v1.len() - 1],
+            &v2[..40],
+            &bad_offset,
+        ] {
+            let result = CarReader::new(car)
+                .and_then(|reader| reader.collect::<Result<Vec<_>, _>>());
+            assert!(
+                matches!(result, Err(Error::InvalidCar(_))),
+                "{:?}",
+                result
+            );
+        }
+
+        let mut reader = CarReader::new(&v2[..v2.len() - 1]).unwrap();
+        assert!(reader.by_ref().all(|block| block.is_ok()));
+        assert!(reader.into_index().is_err());
+    }
+}
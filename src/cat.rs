@@ -1,59 +1,67 @@
-use crate::IpfsApi;
-use std::io::Read;
+use crate::{ByteStream, IpfsApi, Result};
 
 impl IpfsApi {
     /// Retrieves the contents of a file from the IPFS network. Takes a
-    /// hash and returns an iterator of bytes. The result can be streamed, if
-    /// the file is large.
+    /// [`Cid`](crate::cid::Cid) or an IPFS path and returns a reader of the
+    /// contents. The result can be streamed, if the file is large.
     ///
-    /// ```rust
+    /// ```rust,no_run
     /// # use ipfsapi::IpfsApi;
+    /// use std::io::Read;
+    ///
     /// let api = IpfsApi::new("127.0.0.1", 5001);
     ///
-    /// let hello = api.cat("QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u")?;
-    /// let hello_string = String::from_utf8(hello.collect())?;
+    /// let mut hello = api.cat("QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u")?;
+    /// let mut hello_string = String::new();
+    /// hello.read_to_string(&mut hello_string)?;
     /// println!("{}", hello_string);
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub fn cat(
-        &self,
-        hash: &str,
-    ) -> Result<impl Iterator<Item = u8>, ureq::Error> {
+    pub fn cat(&self, path: impl Into<String>) -> Result<ByteStream> {
         crate::rand::feed_event("cat");
-        let url = format!("http://{}:{}/api/v0/cat", self.server, self.port);
-        let req = ureq::post(&url).query("arg", hash);
+        let req = self.post("cat").query("arg", &path.into());
         let resp = req.call()?;
-        let bytes = resp.into_reader().bytes();
 
-        Ok(bytes.filter_map(std::result::Result::ok))
+        Ok(resp.into_stream())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::IpfsApi;
+    use crate::testing::{MockResponse, MockServer};
+    use std::io::Read;
 
     #[test]
     fn test_cat_bytes() {
-        let api = IpfsApi::new("127.0.0.1", 5001);
+        let server = MockServer::start();
+        server.on("cat", MockResponse::stream(["Hello ", "World\n"]));
+        let api = server.api();
 
         // Hello world object
-        let bytes = api
+        let mut stream = api
             .cat("QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u")
             .unwrap();
-        let bytes = bytes.collect::<Vec<u8>>();
+        let mut bytes = Vec::new();
+        stream.read_to_end(&mut bytes).unwrap();
 
         assert_eq!(b"Hello World\n", bytes.as_slice());
+        assert_eq!(
+            server.requests()[0].args(),
+            ["QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u"]
+        );
     }
 
     #[test]
     fn test_cat_string() {
-        let api = IpfsApi::new("127.0.0.1", 5001);
+        let server = MockServer::start();
+        server.on("cat", MockResponse::new(200, "Hello World\n"));
+        let api = server.api();
 
-        let bytes = api
+        let mut stream = api
             .cat("QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u")
             .unwrap();
-        let string = String::from_utf8(bytes.collect()).unwrap();
+        let mut string = String::new();
+        stream.read_to_string(&mut string).unwrap();
 
         assert_eq!("Hello World\n", &string);
     }
@@ -0,0 +1,534 @@
+//! Content identifiers (CIDs), the self-describing addresses of blocks.
+//!
+//! A [`Cid`] can be parsed from its string form. Methods of
+//! [`IpfsApi`](crate::IpfsApi) that take a single block or DAG root take a
+//! `&Cid`, those that also accept IPFS paths take a `Cid` as well, and the
+//! CIDs in responses are returned as `Cid`s.
+//!
+//! ```rust
+//! use ipfsapi::cid::{self, Cid, Version};
+//!
+//! let cid: Cid = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u".parse()?;
+//! assert_eq!(cid.version(), Version::V0);
+//! assert_eq!(cid.codec(), cid::DAG_PB);
+//! assert_eq!(
+//!     cid.to_v1().to_string(),
+//!     "bafybeiduiecxoeiqs3gyc6r7v3lymmhserldnpw62qjnhmqsulqjxjmtzi"
+//! );
+//! # Ok::<(), ipfsapi::Error>(())
+//! ```
+
+use crate::multibase::{self, Base};
+use crate::varint;
+use crate::{Error, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The multicodec code of raw blocks.
+pub const RAW: u64 = 0x55;
+
+/// The multicodec code of DAG-PB, the codec of UnixFS and of all CIDv0.
+pub const DAG_PB: u64 = 0x70;
+
+/// The multicodec code of DAG-CBOR.
+pub const DAG_CBOR: u64 = 0x71;
+
+/// The multicodec code of DAG-JSON.
+pub const DAG_JSON: u64 = 0x0129;
+
+/// The multihash code of the identity function, which inlines the data in
+/// the multihash.
+pub const IDENTITY: u64 = 0x00;
+
+/// The multihash code of SHA2-256, the hash function of all CIDv0.
+pub const SHA2_256: u64 = 0x12;
+
+/// The multihash code of SHA2-512.
+pub const SHA2_512: u64 = 0x13;
+
+/// The multihash code of BLAKE3 with a 256-bit digest.
+pub const BLAKE3: u64 = 0x1e;
+
+/// The multihash code of BLAKE2b with a 256-bit digest.
+pub const BLAKE2B_256: u64 = 0xb220;
+
+fn invalid(msg: &str) -> Error {
+    Error::InvalidCid(msg.into())
+}
+
+/// Reads a varint at the start of `buf`.
+fn read_varint(buf: &[u8], what: &str) -> Result<(u64, usize)> {
+    varint::decode(buf).ok_or_else(|| invalid(&format!("invalid {}", what)))
+}
+
+/// A multihash: the code of a hash function, and a digest made with it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Multihash {
+    code: u64,
+    digest: Vec<u8>,
+}
+
+impl Multihash {
+    /// Creates a multihash from the multicodec code of a hash function and
+    /// a digest.
+    pub fn new(code: u64, digest: impl Into<Vec<u8>>) -> Self {
+        Self {
+            code,
+            digest: digest.into(),
+        }
+    }
+
+    /// Hashes data with SHA2-256.
+    pub fn sha2_256(data: &[u8]) -> Self {
+        use sha2::Digest;
+        Self::new(SHA2_256, sha2::Sha256::digest(data).to_vec())
+    }
+
+    /// Hashes data with the hash function of a multihash code. Supports
+    /// [`IDENTITY`], [`SHA2_256`], [`SHA2_512`], [`BLAKE2B_256`] and
+    /// [`BLAKE3`].
+    pub fn hash(code: u64, data: &[u8]) -> Result<Self> {
+        use sha2::Digest;
+
+        let digest = match code {
+            IDENTITY => data.to_vec(),
+            SHA2_256 => sha2::Sha256::digest(data).to_vec(),
+            SHA2_512 => sha2::Sha512::digest(data).to_vec(),
+            BLAKE2B_256 => {
+                type Blake2b256 = blake2::Blake2b<blake2::digest::consts::U32>;
+                Blake2b256::digest(data).to_vec()
+            }
+            BLAKE3 => blake3::hash(data).as_bytes().to_vec(),
+            _ => {
+                let msg = format!("unsupported hash function 0x{:x}", code);
+                return Err(invalid(&msg));
+            }
+        };
+
+        Ok(Self::new(code, digest))
+    }
+
+    /// Returns whether data hashes to this multihash. Returns an error if
+    /// the hash function is not supported by [`hash`](Self::hash).
+    pub fn matches(&self, data: &[u8]) -> Result<bool> {
+        Ok(Self::hash(self.code, data)? == *self)
+    }
+
+    /// Returns the multicodec code of the hash function, such as `0x12` for
+    /// SHA2-256.
+    pub fn code(&self) -> u64 {
+        self.code
+    }
+
+    /// Returns the digest.
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// Returns the binary form of the multihash.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.digest.len() + 4);
+        varint::encode(self.code, &mut out);
+        varint::encode(self.digest.len() as u64, &mut out);
+        out.extend_from_slice(&self.digest);
+        out
+    }
+
+    /// Decodes a multihash at the start of `buf`, and returns it with its
+    /// length.
+    pub(crate) fn read_bytes(buf: &[u8]) -> Result<(Self, usize)> {
+        let (code, code_len) = read_varint(buf, "multihash code")?;
+        let (len, len_len) = read_varint(&buf[code_len..], "digest length")?;
+
+        let start = code_len + len_len;
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|len| start.checked_add(len))
+            .filter(|&end| end <= buf.len())
+            .ok_or_else(|| invalid("truncated digest"))?;
+
+        Ok((Self::new(code, &buf[start..end]), end))
+    }
+}
+
+/// The version of a CID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Version {
+    /// A CIDv0, a bare SHA2-256 multihash of a DAG-PB block.
+    V0,
+
+    /// A CIDv1, which names its codec and can use any hash function.
+    V1,
+}
+
+impl Version {
+    /// Returns the value of the `cid-version` option of the API.
+    pub(crate) fn as_arg(self) -> &'static str {
+        match self {
+            Version::V0 => "0",
+            Version::V1 => "1",
+        }
+    }
+}
+
+/// A content identifier, made of a version, the multicodec code of the
+/// block's codec and the multihash of the block.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Cid {
+    version: Version,
+    codec: u64,
+    hash: Multihash,
+}
+
+impl Cid {
+    /// Creates a CIDv0. The multihash must be a SHA2-256 digest.
+    pub fn new_v0(hash: Multihash) -> Result<Self> {
+        if hash.code != SHA2_256 || hash.digest.len() != 32 {
+            return Err(invalid("CIDv0 must use a SHA2-256 multihash"));
+        }
+
+        Ok(Self {
+            version: Version::V0,
+            codec: DAG_PB,
+            hash,
+        })
+    }
+
+    /// Creates a CIDv1 from the multicodec code of a codec, such as `0x55`
+    /// for raw blocks, and a multihash.
+    pub fn new_v1(codec: u64, hash: Multihash) -> Self {
+        Self {
+            version: Version::V1,
+            codec,
+            hash,
+        }
+    }
+
+    /// Returns the CIDv1 of the same block.
+    pub fn to_v1(&self) -> Self {
+        Self::new_v1(self.codec, self.hash.clone())
+    }
+
+    /// Returns the CIDv0 of the same block. Only DAG-PB blocks hashed with
+    /// SHA2-256 have a CIDv0.
+    pub fn to_v0(&self) -> Result<Self> {
+        if self.codec != DAG_PB {
+            return Err(invalid("CIDv0 must use the DAG-PB codec"));
+        }
+
+        Self::new_v0(self.hash.clone())
+    }
+
+    /// Returns the version of the CID.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Returns the multicodec code of the block's codec.
+    pub fn codec(&self) -> u64 {
+        self.codec
+    }
+
+    /// Returns the multihash of the block.
+    pub fn hash(&self) -> &Multihash {
+        &self.hash
+    }
+
+    /// Returns the string form of the CID in a multibase, like
+    /// `ipfs cid format -b <base>`. A CIDv0 can only be written in
+    /// base58btc, so convert it with [`to_v1`](Self::to_v1) first.
+    pub fn format(&self, base: Base) -> Result<String> {
+        match self.version {
+            Version::V0 if base == Base::Base58Btc => Ok(self.to_string()),
+            Version::V0 => {
+                Err(invalid("CIDv0 can only be written in base58btc"))
+            }
+            Version::V1 => Ok(multibase::encode(base, &self.to_bytes())),
+        }
+    }
+
+    /// Returns the CIDv1 of the same block in base32, like `ipfs cid base32`.
+    /// This is the form Kubo uses for subdomain gateways.
+    pub fn to_base32(&self) -> String {
+        self.to_v1().to_string()
+    }
+
+    /// Decodes the binary form of a CID.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (cid, len) = Self::read_bytes(bytes)?;
+        if len != bytes.len() {
+            return Err(invalid("trailing bytes after the CID"));
+        }
+
+        Ok(cid)
+    }
+
+    /// Decodes a CID at the start of `buf`, and returns it with its length.
+    pub(crate) fn read_bytes(buf: &[u8]) -> Result<(Self, usize)> {
+        // A CIDv0 is a multihash, starting with the code and length of a
+        // SHA2-256 digest.
+        if buf.starts_with(&[0x12, 0x20]) {
+            let (hash, len) = Multihash::read_bytes(buf)?;
+            return Ok((Self::new_v0(hash)?, len));
+        }
+
+        let (version, version_len) = read_varint(buf, "CID version")?;
+        if version != 1 {
+            let msg = format!("unsupported CID version {}", version);
+            return Err(invalid(&msg));
+        }
+
+        let (codec, codec_len) = read_varint(&buf[version_len..], "codec")?;
+        let start = version_len + codec_len;
+        let (hash, hash_len) = Multihash::read_bytes(&buf[start..])?;
+
+        Ok((Self::new_v1(codec, hash), start + hash_len))
+    }
+
+    /// Returns the binary form of the CID.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self.version {
+            Version::V0 => self.hash.to_bytes(),
+            Version::V1 => {
+                let mut out = vec![0x01];
+                varint::encode(self.codec, &mut out);
+                out.extend_from_slice(&self.hash.to_bytes());
+                out
+            }
+        }
+    }
+}
+
+impl FromStr for Cid {
+    type Err = Error;
+
+    /// Parses a CIDv0, or a CIDv1 in any multibase.
+    fn from_str(s: &str) -> Result<Self> {
+        if s.len() == 46 && s.starts_with("Qm") {
+            return Self::from_bytes(&Base::Base58Btc.decode(s)?);
+        }
+
+        let (_, bytes) = multibase::decode(s)?;
+        let cid = Self::from_bytes(&bytes)?;
+        if cid.version == Version::V0 {
+            let msg = format!("{} is a CIDv0 with a multibase prefix", s);
+            return Err(invalid(&msg));
+        }
+
+        Ok(cid)
+    }
+}
+
+impl TryFrom<&str> for Cid {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl TryFrom<String> for Cid {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<&Cid> for Cid {
+    fn from(cid: &Cid) -> Cid {
+        cid.clone()
+    }
+}
+
+impl Display for Cid {
+    /// Writes a CIDv0 in base58btc, and a CIDv1 in base32, like Kubo.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.version {
+            Version::V0 => {
+                f.write_str(&Base::Base58Btc.encode(&self.to_bytes()))
+            }
+            Version::V1 => {
+                f.write_str(&multibase::encode(Base::Base32, &self.to_bytes()))
+            }
+        }
+    }
+}
+
+impl Serialize for Cid {
+    /// Serializes the CID as a string, like [`Display`].
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cid {
+    /// Deserializes a CID from its string form, like [`FromStr`].
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl From<Cid> for String {
+    fn from(cid: Cid) -> String {
+        cid.to_string()
+    }
+}
+
+impl From<&Cid> for String {
+    fn from(cid: &Cid) -> String {
+        cid.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cid, Multihash, Version};
+    use crate::multibase::Base;
+
+    #[test]
+    fn test_binary_cid() {
+        let digest = [7; 32];
+        let v0 = Cid::new_v0(Multihash::new(0x12, digest)).unwrap();
+        let mut bytes = vec![0x12, 0x20];
+        bytes.extend_from_slice(&digest);
+        assert_eq!(v0.to_bytes(), bytes);
+        assert_eq!(Cid::from_bytes(&bytes).unwrap(), v0);
+
+        let v1 = Cid::new_v1(0x0129, Multihash::new(0x1e, digest));
+        let mut bytes = vec![0x01, 0xa9, 0x02, 0x1e, 0x20];
+        bytes.extend_from_slice(&digest);
+        assert_eq!(v1.to_bytes(), bytes);
+        let decoded = Cid::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.version(), Version::V1);
+        assert_eq!(decoded.codec(), 0x0129);
+        assert_eq!(decoded.hash().code(), 0x1e);
+
+        assert!(Cid::new_v0(Multihash::new(0x13, [0; 64])).is_err());
+        assert!(Cid::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Cid::from_bytes(&[0x02, 0x55, 0x00, 0x00]).is_err());
+        bytes.push(0);
+        assert!(Cid::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let v0 = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u";
+        let v1 = "bafybeiduiecxoeiqs3gyc6r7v3lymmhserldnpw62qjnhmqsulqjxjmtzi";
+
+        let cid: Cid = v0.parse().unwrap();
+        assert_eq!(cid.version(), Version::V0);
+        assert_eq!(cid.codec(), super::DAG_PB);
+        assert_eq!(cid.hash().code(), super::SHA2_256);
+        assert_eq!(cid.to_string(), v0);
+        assert_eq!(cid.to_v1().to_string(), v1);
+
+        for s in [
+            v1,
+            "BAFYBEIDUIECXOEIQS3GYC6R7V3LYMMHSERLDNPW62QJNHMQSULQJXJMTZI",
+            "zdj7WdFhsqBWxrytwZCdqy6d3dQKkifFpVDyANan1Ay38VmJq",
+        ] {
+            let parsed: Cid = s.parse().unwrap();
+            assert_eq!(parsed, cid.to_v1());
+            assert_eq!(parsed.to_v0().unwrap(), cid);
+        }
+
+        // The raw block of "Hello world".
+        let raw: Cid =
+            "bafkreide5semuafsnds3ugrvm6fbwuyw2ijpj43gwjdxemstjkfozi37hq"
+                .parse()
+                .unwrap();
+        assert_eq!(raw.codec(), super::RAW);
+        assert_eq!(raw.hash().digest()[..4], [0x64, 0xec, 0x88, 0xca]);
+        assert!(raw.to_v0().is_err());
+
+        for invalid in [
+            "",
+            "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs80",
+            "bafybeiduiecxoeiqs3gyc6r7v3lymmhserldnpw62qjnhmqsulqjxjmtz",
+            "/ipfs/QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u",
+            "zQmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u",
+        ] {
+            assert!(invalid.parse::<Cid>().is_err(), "{} was parsed", invalid);
+        }
+    }
+
+    #[test]
+    fn test_hash() {
+        let data = b"Hello world";
+        for (code, prefix) in [
+            (super::IDENTITY, &b"Hello"[..]),
+            (super::SHA2_256, &[0x64, 0xec, 0x88, 0xca]),
+            (super::SHA2_512, &[0xb7, 0xf7, 0x83, 0xba]),
+            (super::BLAKE2B_256, &[0xa2, 0x1c, 0xf4, 0xb3]),
+            (super::BLAKE3, &[0xe7, 0xe6, 0xfb, 0x7d]),
+        ] {
+            let hash = Multihash::hash(code, data).unwrap();
+            assert!(hash.digest().starts_with(prefix), "0x{:x}", code);
+            assert!(hash.matches(data).unwrap());
+            assert!(!hash.matches(b"Hello World").unwrap());
+        }
+
+        assert!(Multihash::hash(0x14, data).is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        let v0: Cid = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u"
+            .parse()
+            .unwrap();
+        let v1 = v0.to_v1();
+        assert_eq!(
+            v0.to_base32(),
+            "bafybeiduiecxoeiqs3gyc6r7v3lymmhserldnpw62qjnhmqsulqjxjmtzi"
+        );
+        assert_eq!(v0.format(Base::Base58Btc).unwrap(), v0.to_string());
+        assert!(v0.format(Base::Base32).is_err());
+
+        for base in [
+            Base::Base16,
+            Base::Base32Upper,
+            Base::Base36,
+            Base::Base58Btc,
+            Base::Base64,
+            Base::Base64UrlPad,
+        ] {
+            let formatted = v1.format(base).unwrap();
+            assert!(formatted.starts_with(base.code()));
+            assert_eq!(formatted.parse::<Cid>().unwrap(), v1);
+        }
+        assert_eq!(
+            v1.format(Base::Base58Btc).unwrap(),
+            "zdj7WdFhsqBWxrytwZCdqy6d3dQKkifFpVDyANan1Ay38VmJq"
+        );
+
+        // An IPNS name: the CID of an inlined Ed25519 public key, in base36.
+        let mut key = vec![0x08, 0x01, 0x12, 0x20];
+        key.extend(0..32);
+        let cid = Cid::new_v1(0x72, Multihash::new(0x00, key));
+        let name =
+            "k51qzi5uqu5dg6lcd99r9gmb963kgugjinxxggwy7o93oagk3f2eg3qcjh7127";
+        assert_eq!(cid.format(Base::Base36).unwrap(), name);
+        assert_eq!(name.parse::<Cid>().unwrap(), cid);
+    }
+
+    #[test]
+    fn test_serde() {
+        let cid: Cid = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u"
+            .parse()
+            .unwrap();
+        let json = serde_json::json!(cid);
+        assert_eq!(json, "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u");
+        assert_eq!(serde_json::from_value::<Cid>(json).unwrap(), cid);
+
+        assert!(serde_json::from_str::<Cid>("\"QmHash\"").is_err());
+        assert!(serde_json::from_str::<Cid>("42").is_err());
+    }
+}
@@ -0,0 +1,595 @@
+//! Types for storing and reading IPLD nodes with [`IpfsApi::dag_put`],
+//! [`IpfsApi::dag_get`] and the other `dag` endpoints.
+
+use crate::cid::Cid;
+use crate::multipart::{Multipart, Part};
+use crate::{Error, IpfsApi, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{Read, Write};
+
+/// An IPLD codec, used to encode the nodes that are sent or stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Codec {
+    /// The DAG-JSON codec, where links are written as `{"/": "<cid>"}`.
+    DagJson,
+
+    /// The DAG-CBOR codec, the default codec for storing nodes.
+    DagCbor,
+}
+
+impl Codec {
+    /// Returns the name of the codec in the multicodec table.
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::DagJson => "dag-json",
+            Codec::DagCbor => "dag-cbor",
+        }
+    }
+}
+
+/// Options of the `/api/v0/dag/put` endpoint.
+///
+/// ```rust
+/// use ipfsapi::dag::{Codec, DagPutOptions};
+///
+/// let opts = DagPutOptions::new().store_codec(Codec::DagJson).pin(true);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DagPutOptions {
+    store_codec: Option<Codec>,
+    hash: Option<String>,
+    pin: Option<bool>,
+}
+
+impl DagPutOptions {
+    /// Creates options that store nodes as DAG-CBOR without pinning them.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the codec used to store the node.
+    #[must_use]
+    pub fn store_codec(mut self, codec: Codec) -> Self {
+        self.store_codec = Some(codec);
+        self
+    }
+
+    /// Sets the hash function of the CID, such as `sha2-256`.
+    #[must_use]
+    pub fn hash(mut self, hash: &str) -> Self {
+        self.hash = Some(hash.into());
+        self
+    }
+
+    /// Pins the node.
+    #[must_use]
+    pub fn pin(mut self, pin: bool) -> Self {
+        self.pin = Some(pin);
+        self
+    }
+}
+
+/// The result of resolving a path with [`IpfsApi::dag_resolve`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DagResolveResponse {
+    /// The CID of the last block on the path.
+    pub cid: Cid,
+
+    /// The part of the path inside that block, or an empty string if the
+    /// path ends at the block.
+    pub rem_path: String,
+}
+
+/// The size of a DAG, returned by [`IpfsApi::dag_stat`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DagStat {
+    /// The total size of the blocks of the DAG.
+    pub size: u64,
+
+    /// The number of blocks of the DAG.
+    pub num_blocks: u64,
+}
+
+/// Options of the `/api/v0/dag/import` endpoint.
+///
+/// ```rust
+/// use ipfsapi::dag::DagImportOptions;
+///
+/// let opts = DagImportOptions::new().pin_roots(false);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DagImportOptions {
+    pin_roots: Option<bool>,
+}
+
+impl DagImportOptions {
+    /// Creates options that pin the roots of the imported files.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the roots of the imported files are pinned.
+    #[must_use]
+    pub fn pin_roots(mut self, pin_roots: bool) -> Self {
+        self.pin_roots = Some(pin_roots);
+        self
+    }
+}
+
+/// A root of an imported CAR file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DagImportRoot {
+    /// The CID of the root.
+    pub cid: Cid,
+
+    /// The reason why the root could not be pinned.
+    pub pin_error: Option<String>,
+}
+
+/// The number of blocks imported by [`IpfsApi::dag_import`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DagImportStats {
+    /// The number of imported blocks.
+    pub block_count: u64,
+
+    /// The total size of the imported blocks.
+    pub block_bytes: u64,
+}
+
+/// The result of [`IpfsApi::dag_import`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DagImportResponse {
+    /// The roots of the imported files, which are only reported when they
+    /// are pinned.
+    pub roots: Vec<DagImportRoot>,
+
+    /// The number of imported blocks, if the daemon reported it.
+    pub stats: Option<DagImportStats>,
+}
+
+impl DagImportResponse {
+    fn from_json_lines(
+        lines: impl Iterator<Item = Result<Value>>,
+    ) -> Result<Self> {
+        let mut resp = Self {
+            roots: Vec::new(),
+            stats: None,
+        };
+
+        for line in lines {
+            let line = line?;
+            let root = &line["Root"];
+            if root.is_object() {
+                let pin_error = root["PinErrorMsg"]
+                    .as_str()
+                    .filter(|msg| !msg.is_empty())
+                    .map(String::from);
+                resp.roots.push(DagImportRoot {
+                    cid: parse_link(root, "Cid")?,
+                    pin_error,
+                });
+            }
+
+            let stats = &line["Stats"];
+            if stats.is_object() {
+                let number = |field: &str| {
+                    stats[field].as_u64().ok_or_else(|| {
+                        Error::response(&format!("{} is not a number", field))
+                    })
+                };
+                resp.stats = Some(DagImportStats {
+                    block_count: number("BlockCount")?,
+                    block_bytes: number("BlockBytesCount")?,
+                });
+            }
+        }
+
+        Ok(resp)
+    }
+}
+
+/// Reads a DAG-JSON link, `{"/": "<cid>"}`.
+fn parse_link(val: &Value, field: &str) -> Result<Cid> {
+    val[field]["/"]
+        .as_str()
+        .ok_or_else(|| Error::response(&format!("{} is not a link", field)))?
+        .parse()
+}
+
+impl DagStat {
+    fn from_json(resp: &Value) -> Result<Self> {
+        // Newer daemons report the stats of every argument in DagStats.
+        let stat = match resp["DagStats"].as_array() {
+            Some(stats) => stats
+                .first()
+                .ok_or_else(|| Error::response("DagStats is empty"))?,
+            None => resp,
+        };
+
+        let number = |field: &str| {
+            stat[field].as_u64().ok_or_else(|| {
+                Error::response(&format!("{} is not a number", field))
+            })
+        };
+
+        Ok(Self {
+            size: number("Size")?,
+            num_blocks: number("NumBlocks")?,
+        })
+    }
+}
+
+impl IpfsApi {
+    /// Stores a value as an IPLD node and returns its CID. The value is
+    /// sent as DAG-JSON, so links can be written as `{"/": "<cid>"}`, and
+    /// stored with the codec of `opts`.
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// use ipfsapi::dag::DagPutOptions;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Post {
+    ///     title: String,
+    ///     tags: Vec<String>,
+    /// }
+    ///
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let post = Post {
+    ///     title: "Hello".into(),
+    ///     tags: vec!["ipfs".into()],
+    /// };
+    /// let cid = api.dag_put(&post, &DagPutOptions::new())?;
+    /// let post: Post = api.dag_get(&cid)?;
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/dag/put` API.
+    pub fn dag_put<T: Serialize + ?Sized>(
+        &self,
+        value: &T,
+        opts: &DagPutOptions,
+    ) -> Result<Cid> {
+        let data = serde_json::to_vec(value)?;
+        self.dag_put_bytes(&data, Codec::DagJson, opts)
+    }
+
+    /// Stores a node that is already encoded with `input_codec`, and
+    /// returns its CID.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/dag/put` API.
+    pub fn dag_put_bytes(
+        &self,
+        data: &[u8],
+        input_codec: Codec,
+        opts: &DagPutOptions,
+    ) -> Result<Cid> {
+        crate::rand::feed_event("dag_put");
+
+        let mut body = Multipart::new();
+        body.add_part(Part::bytes("file", data));
+
+        let mut req = self
+            .post("dag/put")
+            .set("Content-Type", &body.content_type())
+            .query("input-codec", input_codec.name());
+        if let Some(codec) = opts.store_codec {
+            req = req.query("store-codec", codec.name());
+        }
+        if let Some(hash) = &opts.hash {
+            req = req.query("hash", hash);
+        }
+        if let Some(pin) = opts.pin {
+            req = req.query("pin", &pin.to_string());
+        }
+
+        let resp = req.send(body.into_reader())?;
+        let resp: Value = serde_json::from_reader(resp.into_reader())?;
+
+        parse_link(&resp, "Cid")
+    }
+
+    /// Reads an IPLD node, or a value inside it, and deserializes it from
+    /// its DAG-JSON form.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/dag/get` API.
+    pub fn dag_get<T: DeserializeOwned>(
+        &self,
+        path: impl Into<String>,
+    ) -> Result<T> {
+        let resp = self
+            .post("dag/get")
+            .query("arg", &path.into())
+            .query("output-codec", Codec::DagJson.name())
+            .call()?;
+
+        Ok(serde_json::from_reader(resp.into_reader())?)
+    }
+
+    /// Resolves a path to the last block on it, and returns the remaining
+    /// path inside that block.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/dag/resolve` API.
+    pub fn dag_resolve(
+        &self,
+        path: impl Into<String>,
+    ) -> Result<DagResolveResponse> {
+        let resp =
+            self.post("dag/resolve").query("arg", &path.into()).call()?;
+        let resp: Value = serde_json::from_reader(resp.into_reader())?;
+
+        let rem_path = resp["RemPath"]
+            .as_str()
+            .ok_or_else(|| Error::response("RemPath is not a string"))?;
+
+        Ok(DagResolveResponse {
+            cid: parse_link(&resp, "Cid")?,
+            rem_path: rem_path.into(),
+        })
+    }
+
+    /// Returns the size and number of blocks of a DAG. The CID can be a
+    /// [`Cid`] or its string form.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/dag/stat` API.
+    pub fn dag_stat(
+        &self,
+        cid: impl TryInto<Cid, Error = impl Into<Error>>,
+    ) -> Result<DagStat> {
+        let cid: Cid = cid.try_into().map_err(Into::into)?;
+        let resp = self
+            .post("dag/stat")
+            .query("arg", &cid.to_string())
+            .query("progress", "false")
+            .call()?;
+        let resp: Value = serde_json::from_reader(resp.into_reader())?;
+
+        DagStat::from_json(&resp)
+    }
+
+    /// Exports the DAG under a root as a CARv1 file, streamed to `writer`,
+    /// and returns the size of the file. The file can be read with
+    /// [`CarReader`](crate::car::CarReader).
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// use std::fs::File;
+    ///
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let root = "QmS4ustL54uo8FzR9455qaxZwuMiUhyvMcX9Ba8nUH4uVv".parse()?;
+    /// api.dag_export(&root, File::create("site.car")?)?;
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/dag/export` API.
+    pub fn dag_export(
+        &self,
+        root: &Cid,
+        mut writer: impl Write,
+    ) -> Result<u64> {
+        let resp = self
+            .post("dag/export")
+            .query("arg", &root.to_string())
+            .query("progress", "false")
+            .call()?;
+
+        let size = std::io::copy(&mut resp.into_stream(), &mut writer)?;
+        writer.flush()?;
+        Ok(size)
+    }
+
+    /// Imports the blocks of a CARv1 or CARv2 file, streamed from `car`.
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// use ipfsapi::dag::DagImportOptions;
+    /// use std::fs::File;
+    ///
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let opts = DagImportOptions::new();
+    /// let imported = api.dag_import(File::open("site.car")?, &opts)?;
+    /// for root in imported.roots {
+    ///     println!("{} {:?}", root.cid, root.pin_error);
+    /// }
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/dag/import` API.
+    pub fn dag_import(
+        &self,
+        car: impl Read + Send,
+        opts: &DagImportOptions,
+    ) -> Result<DagImportResponse> {
+        crate::rand::feed_event("dag_import");
+
+        let mut body = Multipart::new();
+        body.add_part(
+            Part::new("file", car)
+                .filename("")
+                .header("Content-Type", "application/octet-stream"),
+        );
+
+        let mut req = self
+            .post("dag/import")
+            .set("Content-Type", &body.content_type())
+            .query("stats", "true");
+        if let Some(pin_roots) = opts.pin_roots {
+            req = req.query("pin-roots", &pin_roots.to_string());
+        }
+
+        let resp = req.send(body.into_reader())?;
+        DagImportResponse::from_json_lines(resp.into_json_lines())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Codec, DagImportOptions, DagPutOptions, DagStat};
+    use crate::car::{CarReader, CarWriter};
+    use crate::cid::{Cid, Multihash};
+    use crate::testing::{MockResponse, MockServer};
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Post {
+        title: String,
+        parent: Option<serde_json::Value>,
+    }
+
+    #[test]
+    fn test_dag_put_and_get() {
+        let cid = "bafyreigbtj4x7ip5legnfznufuopl4sg4knzc2cof6duas4b3q2fy6swua";
+        let server = MockServer::start();
+        server.on("dag/put", MockResponse::json(&json!({"Cid": {"/": cid}})));
+        server.on(
+            "dag/get",
+            MockResponse::json(&json!({
+                "title": "Hello",
+                "parent": {"/": "bafkqaaa"},
+            })),
+        );
+
+        let post = Post {
+            title: "Hello".into(),
+            parent: Some(json!({"/": "bafkqaaa"})),
+        };
+        let api = server.api();
+        let opts = DagPutOptions::new().store_codec(Codec::DagCbor).pin(true);
+        assert_eq!(api.dag_put(&post, &opts).unwrap().to_string(), cid);
+        assert_eq!(api.dag_get::<Post>(cid).unwrap(), post);
+
+        let requests = server.requests();
+        assert_eq!(requests[0].query_value("input-codec"), Some("dag-json"));
+        assert_eq!(requests[0].query_value("store-codec"), Some("dag-cbor"));
+        assert_eq!(requests[0].query_value("pin"), Some("true"));
+        let part = &requests[0].parts().unwrap()[0];
+        let sent: Post = serde_json::from_slice(&part.body).unwrap();
+        assert_eq!(sent, post);
+        assert_eq!(requests[1].query_value("output-codec"), Some("dag-json"));
+    }
+
+    #[test]
+    fn test_dag_resolve() {
+        let server = MockServer::start();
+        server.on(
+            "dag/resolve",
+            MockResponse::json(&json!({
+                "Cid": {"/": "bafyreigbtj4x7ip5legnfznufuopl4sg4knzc2cof6duas4b3q2fy6swua"},
+                "RemPath": "title",
+            })),
+        );
+
+        let resolved = server
+            .api()
+            .dag_resolve(
+                "/ipfs/QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u/post",
+            )
+            .unwrap();
+        assert_eq!(
+            resolved.cid.to_string(),
+            "bafyreigbtj4x7ip5legnfznufuopl4sg4knzc2cof6duas4b3q2fy6swua"
+        );
+        assert_eq!(resolved.rem_path, "title");
+    }
+
+    #[test]
+    fn test_dag_stat() {
+        let cid: Cid =
+            "bafyreigbtj4x7ip5legnfznufuopl4sg4knzc2cof6duas4b3q2fy6swua"
+                .parse()
+                .unwrap();
+        let new = json!({
+            "UniqueBlocks": 2,
+            "TotalSize": 300,
+            "Ratio": 1,
+            "DagStats": [{"Cid": {"/": cid}, "Size": 300, "NumBlocks": 2}],
+        });
+        let old = json!({"Size": 300, "NumBlocks": 2});
+
+        for resp in [new, old] {
+            let server = MockServer::start();
+            server.on("dag/stat", MockResponse::json(&resp));
+
+            let stat = server.api().dag_stat(&cid).unwrap();
+            assert_eq!(
+                stat,
+                DagStat {
+                    size: 300,
+                    num_blocks: 2,
+                }
+            );
+        }
+    }
+
+    fn car() -> (Cid, Vec<u8>) {
+        let cid = Cid::new_v1(0x55, Multihash::new(0x12, [1; 32]));
+        let mut writer =
+            CarWriter::new(Vec::new(), std::slice::from_ref(&cid)).unwrap();
+        writer.write_block(&cid, b"Hello world").unwrap();
+        (cid, writer.finish().unwrap())
+    }
+
+    #[test]
+    fn test_dag_export() {
+        let (cid, car) = car();
+        let server = MockServer::start();
+        server.on("dag/export", MockResponse::stream([car.clone()]));
+
+        let mut exported = Vec::new();
+        let size = server.api().dag_export(&cid, &mut exported).unwrap();
+        assert_eq!(size, car.len() as u64);
+
+        let mut reader = CarReader::new(&exported[..]).unwrap();
+        assert_eq!(reader.roots(), std::slice::from_ref(&cid));
+        let block = reader.next().unwrap().unwrap();
+        assert_eq!(block, (cid, b"Hello world".to_vec()));
+    }
+
+    #[test]
+    fn test_dag_import() {
+        let (_, car) = car();
+        let server = MockServer::start();
+        server.on(
+            "dag/import",
+            MockResponse::ndjson([
+                json!({"Root": {"Cid": {"/": "bafkreide5semuafsnds3ugrvm6fbwuyw2ijpj43gwjdxemstjkfozi37hq"}, "PinErrorMsg": ""}}),
+                json!({"Root": {"Cid": {"/": "bafyreigbtj4x7ip5legnfznufuopl4sg4knzc2cof6duas4b3q2fy6swua"}, "PinErrorMsg": "not found"}}),
+                json!({"Stats": {"BlockCount": 1, "BlockBytesCount": 11}}),
+            ]),
+        );
+
+        let opts = DagImportOptions::new().pin_roots(true);
+        let imported = server.api().dag_import(&car[..], &opts).unwrap();
+        assert_eq!(imported.roots.len(), 2);
+        assert_eq!(
+            imported.roots[0].cid.to_string(),
+            "bafkreide5semuafsnds3ugrvm6fbwuyw2ijpj43gwjdxemstjkfozi37hq"
+        );
+        assert_eq!(imported.roots[0].pin_error, None);
+        assert_eq!(imported.roots[1].pin_error.as_deref(), Some("not found"));
+        let stats = imported.stats.unwrap();
+        assert_eq!((stats.block_count, stats.block_bytes), (1, 11));
+
+        let request = &server.requests()[0];
+        assert_eq!(request.query_value("pin-roots"), Some("true"));
+        assert_eq!(request.query_value("stats"), Some("true"));
+        assert_eq!(request.parts().unwrap()[0].body, car);
+    }
+}
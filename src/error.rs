@@ -0,0 +1,242 @@
+use std::fmt::Display;
+use std::io::Read;
+
+/// Result type returned by the API methods.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The error type shared by every API method.
+///
+/// Failures to reach the daemon are reported as [`Error::Transport`], while
+/// errors reported by the daemon itself are decoded from Kubo's JSON error
+/// body into [`Error::Api`]. This makes it possible to tell "block not found"
+/// apart from "daemon down" without matching on strings.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The request could not be sent or the response could not be received,
+    /// for example because the daemon is not running.
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+
+    /// The daemon replied with an unexpected HTTP status and a body that is
+    /// not a Kubo error object.
+    Status { code: u16, body: String },
+
+    /// The daemon reported an error using its `{"Message","Code","Type"}`
+    /// error object. The code is kept as sent, since Kubo does not bound
+    /// it.
+    Api {
+        message: String,
+        code: i64,
+        kind: String,
+    },
+
+    /// The response body could not be decoded as JSON.
+    Json(serde_json::Error),
+
+    /// An I/O error happened while reading or writing a body.
+    Io(std::io::Error),
+
+    /// The daemon reported an error after it had started sending a streamed
+    /// response, using the `X-Stream-Error` trailer.
+    ///
+    /// The message is the daemon's, except with the default HTTP transport:
+    /// ureq cannot read trailers, so the message is then a fixed text saying
+    /// that the daemon reported an error. The Unix socket transport and
+    /// [`AsyncIpfsApi`](crate::AsyncIpfsApi) return the daemon's message.
+    Stream(String),
+
+    /// The response was valid JSON, but it did not have the expected shape.
+    Response(String),
+
+    /// An API address could not be parsed as a supported multiaddr.
+    InvalidMultiaddr(String),
+
+    /// A CID or multihash could not be decoded.
+    InvalidCid(String),
+
+    /// A multibase string has an unknown prefix or invalid characters.
+    InvalidMultibase(String),
+
+    /// A CAR file is malformed or uses an unsupported format.
+    InvalidCar(String),
+
+    /// A block does not match the multihash of its CID.
+    Integrity(String),
+
+    /// An argument, a local file or an archive entry was rejected before it
+    /// was used, such as an MFS directory opened as a file, or an entry of
+    /// [`IpfsApi::get_to`](crate::IpfsApi::get_to) outside the destination.
+    InvalidInput(String),
+}
+
+impl Error {
+    /// Builds an error from a non-successful HTTP response body. Bodies in
+    /// Kubo's error format become [`Error::Api`], everything else becomes
+    /// [`Error::Status`].
+    pub(crate) fn from_status(code: u16, body: String) -> Error {
+        let json: serde_json::Value = match serde_json::from_str(&body) {
+            Ok(json) => json,
+            Err(_) => return Error::Status { code, body },
+        };
+
+        match (json["Message"].as_str(), json["Code"].as_i64()) {
+            (Some(message), Some(api_code)) => Error::Api {
+                message: message.into(),
+                code: api_code,
+                kind: json["Type"].as_str().unwrap_or("error").into(),
+            },
+            _ => Error::Status { code, body },
+        }
+    }
+
+    /// Creates an [`Error::Response`] with the given message.
+    pub(crate) fn response(msg: &str) -> Error {
+        Error::Response(msg.into())
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Transport(e) => write!(f, "transport error: {}", e),
+            Error::Status { code, body } => {
+                write!(f, "unexpected HTTP status {}: {}", code, body)
+            }
+            Error::Api { message, .. } => {
+                write!(f, "IPFS API error: {}", message)
+            }
+            Error::Json(e) => write!(f, "invalid JSON response: {}", e),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Stream(msg) => write!(f, "error while streaming: {}", msg),
+            Error::Response(msg) => write!(f, "unexpected response: {}", msg),
+            Error::InvalidMultiaddr(msg) => {
+                write!(f, "invalid multiaddr: {}", msg)
+            }
+            Error::InvalidCid(msg) => write!(f, "invalid CID: {}", msg),
+            Error::InvalidMultibase(msg) => {
+                write!(f, "invalid multibase: {}", msg)
+            }
+            Error::InvalidCar(msg) => write!(f, "invalid CAR file: {}", msg),
+            Error::Integrity(msg) => {
+                write!(f, "integrity check failed: {}", msg)
+            }
+            Error::InvalidInput(msg) => write!(f, "invalid input: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(e) => Some(e.as_ref()),
+            Error::Json(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ureq::Error> for Error {
+    fn from(err: ureq::Error) -> Error {
+        match err {
+            ureq::Error::Status(code, resp) => {
+                let mut body = String::new();
+                let _ = resp.into_reader().read_to_string(&mut body);
+                Error::from_status(code, body)
+            }
+            ureq::Error::Transport(transport) => {
+                Error::Transport(Box::new(transport))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Error {
+        Error::Transport(Box::new(err))
+    }
+}
+
+impl From<std::convert::Infallible> for Error {
+    fn from(err: std::convert::Infallible) -> Error {
+        match err {}
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        // Errors returned through `Read` implementations are wrapped in I/O
+        // errors, unwrap them again.
+        if err.get_ref().is_some_and(|e| e.is::<Error>()) {
+            if let Some(inner) = err.into_inner() {
+                if let Ok(e) = inner.downcast::<Error>() {
+                    return *e;
+                }
+            }
+            unreachable!("checked that the inner error is an Error");
+        }
+
+        Error::Io(err)
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> std::io::Error {
+        match err {
+            Error::Io(e) => e,
+            e => std::io::Error::other(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+
+    #[test]
+    fn test_api_error_body() {
+        let body = r#"{"Message":"block was not found locally (offline): ipld: could not find QmV8cfu6n4NT5xRr2AHdKxFMTZEJrA44qgrBCr739BN9Wb","Code":0,"Type":"error"}"#;
+
+        match Error::from_status(500, body.into()) {
+            Error::Api {
+                message,
+                code,
+                kind,
+            } => {
+                assert!(message.starts_with("block was not found locally"));
+                assert_eq!(code, 0);
+                assert_eq!(kind, "error");
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_api_error_code_range() {
+        for api_code in [-1, i64::from(u32::MAX) + 1] {
+            let body = format!(r#"{{"Message":"x","Code":{}}}"#, api_code);
+            match Error::from_status(500, body) {
+                Error::Api { code, .. } => assert_eq!(code, api_code),
+                e => panic!("unexpected error: {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_non_api_error_body() {
+        match Error::from_status(404, "404 page not found".into()) {
+            Error::Status { code, body } => {
+                assert_eq!(code, 404);
+                assert_eq!(body, "404 page not found");
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+}
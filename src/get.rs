@@ -0,0 +1,326 @@
+//! Types for downloading files and directories with [`IpfsApi::get`].
+
+use crate::{ByteStream, Error, IpfsApi, Result};
+use flate2::read::GzDecoder;
+use std::io::Read;
+use std::path::{Component, Path};
+use tar::EntryType;
+
+/// Options of the `/api/v0/get` endpoint.
+///
+/// ```rust
+/// use ipfsapi::get::GetOptions;
+///
+/// let opts = GetOptions::new().compress(true);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct GetOptions {
+    compress: bool,
+    compression_level: Option<u32>,
+}
+
+impl GetOptions {
+    /// Creates options that download an uncompressed archive.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the daemon compress the archive with gzip. The archive is
+    /// decompressed as it is received.
+    #[must_use]
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Sets the gzip compression level, from 1 to 9, and enables
+    /// compression.
+    #[must_use]
+    pub fn compression_level(mut self, level: u32) -> Self {
+        self.compress = true;
+        self.compression_level = Some(level);
+        self
+    }
+}
+
+/// Checks that the path of an entry stays inside the destination directory.
+fn check_path(path: &Path) -> Result<()> {
+    for component in path.components() {
+        match component {
+            Component::Normal(_) | Component::CurDir => {}
+            _ => {
+                let msg =
+                    format!("{} is outside the destination", path.display());
+                return Err(Error::InvalidInput(msg));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Extracts a tar archive into `dest`. Entries with `..` or absolute paths,
+/// and entries that are not files, directories or symbolic links, are
+/// rejected.
+fn extract(reader: impl Read, dest: &Path) -> Result<()> {
+    std::fs::create_dir_all(dest)?;
+
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+
+        match entry.header().entry_type() {
+            EntryType::Regular | EntryType::Directory | EntryType::Symlink => {}
+            EntryType::XGlobalHeader => continue,
+            other => {
+                let msg = format!(
+                    "{} has an unsupported type {:?}",
+                    path.display(),
+                    other
+                );
+                return Err(Error::InvalidInput(msg));
+            }
+        }
+
+        check_path(&path)?;
+
+        // This also refuses to write through symbolic links that point
+        // outside of the destination.
+        if !entry.unpack_in(dest)? {
+            let msg = format!("{} is outside the destination", path.display());
+            return Err(Error::InvalidInput(msg));
+        }
+    }
+
+    Ok(())
+}
+
+impl IpfsApi {
+    /// Downloads a file or directory as a tar archive. If compression is
+    /// enabled in `opts`, the archive is decompressed as it is read.
+    ///
+    /// The archive can be read with any tar reader, or extracted with
+    /// [`get_to`](Self::get_to).
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/get` API.
+    pub fn get(
+        &self,
+        path: impl Into<String>,
+        opts: &GetOptions,
+    ) -> Result<ByteStream> {
+        crate::rand::feed_event("get");
+
+        let mut req = self
+            .post("get")
+            .query("arg", &path.into())
+            .query("archive", "true");
+        if opts.compress {
+            req = req.query("compress", "true");
+        }
+        if let Some(level) = opts.compression_level {
+            req = req.query("compression-level", &level.to_string());
+        }
+
+        let stream = req.call()?.into_stream();
+        if opts.compress {
+            return Ok(ByteStream::new(Box::new(GzDecoder::new(stream))));
+        }
+
+        Ok(stream)
+    }
+
+    /// Downloads a file or directory and extracts it into `dest`. The root
+    /// is created in `dest` under the last component of `path`.
+    ///
+    /// Symbolic links are created as links, and the permission bits of files
+    /// are applied. Entries that would be written outside of `dest` make the
+    /// extraction fail.
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// use ipfsapi::get::GetOptions;
+    ///
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let hash = "QmS4ustL54uo8FzR9455qaxZwuMiUhyvMcX9Ba8nUH4uVv";
+    /// api.get_to(hash, "downloads", &GetOptions::new().compress(true))?;
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/get` API.
+    pub fn get_to(
+        &self,
+        path: impl Into<String>,
+        dest: impl AsRef<Path>,
+        opts: &GetOptions,
+    ) -> Result<()> {
+        let stream = self.get(path, opts)?;
+        extract(stream, dest.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GetOptions;
+    use crate::testing::{MockResponse, MockServer};
+    use crate::Error;
+    use std::io::{Read, Write};
+    use std::path::PathBuf;
+
+    fn archive() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        let mut header = tar::Header::new_ustar();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_size(0);
+        builder
+            .append_data(&mut header, "QmRoot", std::io::empty())
+            .unwrap();
+
+        let mut header = tar::Header::new_ustar();
+        header.set_mode(0o750);
+        header.set_size(5);
+        builder
+            .append_data(&mut header, "QmRoot/run.sh", &b"echo\n"[..])
+            .unwrap();
+
+        let mut header = tar::Header::new_ustar();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "QmRoot/link", "run.sh")
+            .unwrap();
+
+        builder.into_inner().unwrap()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "ipfsapi-get-{}-{}",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn test_get() {
+        let server = MockServer::start();
+        server.on("get", MockResponse::new(200, archive()));
+
+        let mut stream =
+            server.api().get("QmRoot", &GetOptions::new()).unwrap();
+        let mut tar = Vec::new();
+        stream.read_to_end(&mut tar).unwrap();
+
+        assert_eq!(tar, archive());
+        assert_eq!(server.requests()[0].args(), ["QmRoot"]);
+        assert_eq!(server.requests()[0].query_value("archive"), Some("true"));
+    }
+
+    #[test]
+    fn test_get_to_compressed() {
+        let mut gzip = flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        );
+        gzip.write_all(&archive()).unwrap();
+
+        let server = MockServer::start();
+        server.on("get", MockResponse::new(200, gzip.finish().unwrap()));
+
+        let dest = temp_dir("compressed");
+        let opts = GetOptions::new().compression_level(6);
+        server.api().get_to("QmRoot", &dest, &opts).unwrap();
+
+        let script = std::fs::read_to_string(dest.join("QmRoot/run.sh"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let meta = std::fs::metadata(dest.join("QmRoot/run.sh")).unwrap();
+            assert_eq!(meta.permissions().mode() & 0o777, 0o750);
+
+            let link = std::fs::read_link(dest.join("QmRoot/link")).unwrap();
+            assert_eq!(link, PathBuf::from("run.sh"));
+        }
+        std::fs::remove_dir_all(&dest).unwrap();
+
+        assert_eq!(script.unwrap(), "echo\n");
+
+        let request = &server.requests()[0];
+        assert_eq!(request.query_value("compress"), Some("true"));
+        assert_eq!(request.query_value("compression-level"), Some("6"));
+    }
+
+    #[test]
+    fn test_get_to_rejects_parent_paths() {
+        for name in ["../evil.txt", "/tmp/evil.txt"] {
+            // tar::Builder refuses to write such paths, so the name is set
+            // directly in the header.
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..name.len()]
+                .copy_from_slice(name.as_bytes());
+            header.set_mode(0o644);
+            header.set_size(4);
+            header.set_cksum();
+
+            let mut builder = tar::Builder::new(Vec::new());
+            builder.append(&header, &b"evil"[..]).unwrap();
+            let tar = builder.into_inner().unwrap();
+
+            let server = MockServer::start();
+            server.on("get", MockResponse::new(200, tar));
+
+            let dest = temp_dir("parent");
+            let result =
+                server.api().get_to("QmEvil", &dest, &GetOptions::new());
+            let escaped = dest.join(name).exists();
+            std::fs::remove_dir_all(&dest).unwrap();
+
+            assert!(
+                matches!(result, Err(Error::InvalidInput(_))),
+                "{} was extracted",
+                name
+            );
+            assert!(!escaped);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_to_rejects_writes_through_symlinks() {
+        let outside = temp_dir("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_ustar();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "QmRoot/out", &outside)
+            .unwrap();
+        let mut header = tar::Header::new_ustar();
+        header.set_mode(0o644);
+        header.set_size(4);
+        builder
+            .append_data(&mut header, "QmRoot/out/evil.txt", &b"evil"[..])
+            .unwrap();
+
+        let server = MockServer::start();
+        server.on("get", MockResponse::new(200, builder.into_inner().unwrap()));
+
+        let dest = temp_dir("symlink");
+        let result = server.api().get_to("QmRoot", &dest, &GetOptions::new());
+        let escaped = outside.join("evil.txt").exists();
+        std::fs::remove_dir_all(&dest).unwrap();
+        std::fs::remove_dir_all(&outside).unwrap();
+
+        assert!(result.is_err());
+        assert!(!escaped);
+    }
+}
@@ -0,0 +1,182 @@
+// Parts of HTTP/1.1 shared by the Unix socket transport and the mock
+// server in `testing`.
+
+use std::io::{BufRead, Read};
+
+fn read_line(reader: &mut impl BufRead) -> std::io::Result<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).into())
+}
+
+/// Reads the start line and the headers of a request or a response.
+pub(crate) fn read_head(
+    reader: &mut impl BufRead,
+) -> std::io::Result<(String, Vec<(String, String)>)> {
+    let start_line = read_line(reader)?;
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().into(), value.trim().into()));
+        }
+    }
+
+    Ok((start_line, headers))
+}
+
+/// Returns the value of the first header with the given name, ignoring case.
+pub(crate) fn header<'a>(
+    headers: &'a [(String, String)],
+    name: &str,
+) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Returns a reader for the body that follows the given headers, decoding
+/// chunked transfer encoding. Without either `Transfer-Encoding` or
+/// `Content-Length`, the body is `rest` until the connection is closed.
+pub(crate) fn body_reader<R>(
+    rest: R,
+    headers: &[(String, String)],
+) -> std::io::Result<Box<dyn Read + Send + Sync>>
+where
+    R: BufRead + Send + Sync + 'static,
+{
+    if header(headers, "Transfer-Encoding")
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"))
+    {
+        Ok(Box::new(ChunkedReader::new(rest)))
+    } else if let Some(len) = header(headers, "Content-Length") {
+        let len = len.parse().map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "invalid Content-Length",
+            )
+        })?;
+        Ok(Box::new(LengthReader::new(rest, len)))
+    } else {
+        Ok(Box::new(rest))
+    }
+}
+
+/// Decodes a body sent with `Transfer-Encoding: chunked`.
+struct ChunkedReader<R> {
+    inner: R,
+    remaining: u64,
+    done: bool,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            remaining: 0,
+            done: false,
+        }
+    }
+
+    fn invalid(msg: &str) -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+    }
+
+    /// Reads the next chunk size line, and the trailers after the last one.
+    /// An `X-Stream-Error` trailer is returned as an error.
+    fn next_chunk(&mut self) -> std::io::Result<()> {
+        let line = read_line(&mut self.inner)?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        self.remaining = u64::from_str_radix(size, 16)
+            .map_err(|_| Self::invalid("invalid chunk size"))?;
+
+        if self.remaining == 0 {
+            self.done = true;
+
+            let mut stream_error = None;
+            loop {
+                let line = read_line(&mut self.inner)?;
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.trim().eq_ignore_ascii_case("X-Stream-Error") {
+                        stream_error = Some(value.trim().to_string());
+                    }
+                }
+            }
+
+            if let Some(msg) = stream_error {
+                return Err(crate::stream::stream_error(&msg));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            self.next_chunk()?;
+            if self.done {
+                return Ok(0);
+            }
+        }
+
+        let max = buf.len().min(self.remaining as usize);
+        let n = self.inner.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+
+        self.remaining -= n as u64;
+        if self.remaining == 0 && !read_line(&mut self.inner)?.is_empty() {
+            return Err(Self::invalid("missing CRLF after chunk"));
+        }
+
+        Ok(n)
+    }
+}
+
+/// Reads a body with a known length, and fails if the connection is closed
+/// before all of it has been received.
+struct LengthReader<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R> LengthReader<R> {
+    fn new(inner: R, len: u64) -> Self {
+        Self {
+            inner,
+            remaining: len,
+        }
+    }
+}
+
+impl<R: Read> Read for LengthReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let max = buf.len().min(self.remaining as usize);
+        let n = self.inner.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
@@ -1,12 +1,11 @@
-use crate::IpfsApi;
-use std::error::Error;
+use crate::{IpfsApi, Result};
 
 impl IpfsApi {
     /// Publish an IPFS hash in IPNS.
-    pub fn name_publish(&self, hash: &str) -> Result<(), Box<dyn Error>> {
-        let endpoint = "api/v0/name/publish";
-        let url = format!("http://{}:{}/{}", self.server, self.port, endpoint);
-        ureq::post(&url).query("arg", hash).call()?;
+    pub fn name_publish(&self, hash: impl Into<String>) -> Result<()> {
+        self.post("name/publish")
+            .query("arg", &hash.into())
+            .call()?;
 
         Ok(())
     }
@@ -1,12 +1,10 @@
-use crate::str_error::StrError;
-use crate::IpfsApi;
+use crate::{Error, IpfsApi, Result};
 use serde_json::Value;
-use std::error::Error;
 
 impl IpfsApi {
     /// Resolve an IPNS hash or a domain name
     ///
-    /// ```rust
+    /// ```rust,no_run
     /// # use ipfsapi::IpfsApi;
     /// let api = IpfsApi::new("127.0.0.1", 5001);
     /// let hash = api.name_resolve("gkbrk.com")?;
@@ -14,15 +12,17 @@ impl IpfsApi {
     /// println!("{}", hash);
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub fn name_resolve(&self, name: &str) -> Result<String, Box<dyn Error>> {
-        let endpoint = "api/v0/name/resolve";
-        let url = format!("http://{}:{}/{}", self.server, self.port, endpoint);
-        let resp = ureq::post(&url).query("arg", name).call()?;
+    pub fn name_resolve(&self, name: &str) -> Result<String> {
+        let resp = self.post("name/resolve").query("arg", name).call()?;
         let resp: Value = serde_json::from_reader(resp.into_reader())?;
 
-        match &resp["Path"] {
-            Value::String(x) => Ok(x.into()),
-            _ => Err(StrError::from_str("Response does not contain 'Path'")),
-        }
+        parse_path(&resp)
+    }
+}
+
+pub(crate) fn parse_path(resp: &Value) -> Result<String> {
+    match &resp["Path"] {
+        Value::String(x) => Ok(x.into()),
+        _ => Err(Error::response("Response does not contain 'Path'")),
     }
 }
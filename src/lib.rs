@@ -2,11 +2,50 @@
 //! This is a crate for interfacing with the local IPFS API. It allows you to
 //! read and write data to the IPFS network.
 
+#[cfg(feature = "async")]
+mod async_api;
+mod auth;
+mod builder;
+pub mod car;
+pub mod cid;
+mod error;
+#[cfg(any(unix, feature = "testing"))]
+mod http1;
+mod multiaddr;
+pub mod multibase;
+pub mod multipart;
 mod pearson;
 mod rand;
-mod str_error;
+mod request;
+mod stream;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod transport;
+#[cfg(unix)]
+mod unix;
+pub mod unixfs;
+mod varint;
 
+#[cfg(feature = "async")]
+pub use async_api::AsyncIpfsApi;
+pub use auth::Auth;
+pub use builder::{IpfsApiBuilder, DEFAULT_BASE_URL};
+pub use error::{Error, Result};
+pub use stream::{ByteStream, Chunks};
+
+use request::RequestBuilder;
+use std::sync::Arc;
+use transport::Transport;
+
+pub mod add;
+mod add_path;
 mod cat;
+pub mod dag;
+pub mod get;
+pub mod ls;
+pub mod mfs;
+mod mfs_sync;
+pub mod refs;
 mod shutdown;
 pub mod version;
 
@@ -25,9 +64,11 @@ mod pin_add;
 //pub mod pubsub;
 //mod log;
 
+#[derive(Clone, Debug)]
 pub struct IpfsApi {
-    server: String,
-    port: u16,
+    transport: Arc<dyn Transport>,
+    headers: Vec<(String, String)>,
+    auth: Option<Auth>,
 }
 
 /// The main interface of the library
@@ -42,9 +83,51 @@ impl IpfsApi {
     /// ```
     #[must_use]
     pub fn new(server: &str, port: u16) -> Self {
-        Self {
-            server: server.into(),
-            port,
-        }
+        let base_url = format!("http://{}:{}/api/v0", server, port);
+        Self::builder().base_url(&base_url).build()
+    }
+
+    /// Creates an instance of the API for the daemon at the given multiaddr,
+    /// such as `/ip4/127.0.0.1/tcp/5001` or `/unix/run/ipfs.sock`.
+    ///
+    /// ```rust
+    /// # use ipfsapi::IpfsApi;
+    /// let api = IpfsApi::from_multiaddr("/ip4/127.0.0.1/tcp/5001")?;
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the multiaddr cannot be parsed.
+    pub fn from_multiaddr(addr: &str) -> Result<Self> {
+        Ok(Self::builder().multiaddr(addr)?.build())
+    }
+
+    /// Creates an instance of the API for the local daemon, using the address
+    /// it advertises in `$IPFS_PATH/api` (or `~/.ipfs/api`).
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, which usually means the
+    /// daemon is not running, or if its contents cannot be parsed.
+    pub fn from_env() -> Result<Self> {
+        Ok(Self::builder().from_env()?.build())
+    }
+
+    /// Creates a builder for configuring the base URL, timeouts and headers
+    /// of the API.
+    #[must_use]
+    pub fn builder() -> IpfsApiBuilder {
+        IpfsApiBuilder::default()
+    }
+
+    /// Returns the transport that delivers the requests of this API.
+    #[must_use]
+    pub fn transport(&self) -> &Arc<dyn Transport> {
+        &self.transport
+    }
+
+    /// Creates a POST request to an API endpoint with the default headers
+    /// applied.
+    fn post<'a>(&'a self, endpoint: &'a str) -> RequestBuilder<'a> {
+        RequestBuilder::new(self, endpoint)
     }
 }
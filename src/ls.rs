@@ -0,0 +1,265 @@
+//! Types for listing directories with [`IpfsApi::ls`].
+
+use crate::cid::Cid;
+use crate::stream::JsonLines;
+use crate::{Error, IpfsApi, Result};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::io::Read;
+
+/// The type of a UnixFS entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FileType {
+    /// A file, stored as UnixFS nodes or raw blocks.
+    File,
+
+    /// A directory, including sharded directories.
+    Directory,
+
+    /// A symbolic link.
+    Symlink,
+
+    /// Another type, with its UnixFS type code. The daemon reports `-1`
+    /// when the type was not resolved.
+    Other(i64),
+}
+
+impl FileType {
+    /// Returns the type for a UnixFS type code.
+    pub(crate) fn from_code(code: i64) -> Self {
+        match code {
+            0 | 2 => FileType::File,
+            1 | 5 => FileType::Directory,
+            4 => FileType::Symlink,
+            other => FileType::Other(other),
+        }
+    }
+}
+
+/// An entry of a directory listed by [`IpfsApi::ls`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LsEntry {
+    /// The name of the entry in the directory.
+    pub name: String,
+
+    /// The CID of the entry.
+    pub hash: Cid,
+
+    /// The size of the file, or 0 for directories.
+    pub size: u64,
+
+    /// The type of the entry.
+    pub file_type: FileType,
+
+    /// The target of a symbolic link.
+    pub target: Option<String>,
+}
+
+impl LsEntry {
+    fn from_json(link: &Value) -> Result<Self> {
+        let string = |field: &str| {
+            link[field].as_str().map(String::from).ok_or_else(|| {
+                Error::response(&format!("{} is not a string", field))
+            })
+        };
+
+        let target = link["Target"]
+            .as_str()
+            .filter(|target| !target.is_empty())
+            .map(String::from);
+
+        Ok(Self {
+            name: string("Name")?,
+            hash: string("Hash")?.parse()?,
+            size: link["Size"]
+                .as_u64()
+                .ok_or_else(|| Error::response("Size is not a number"))?,
+            file_type: FileType::from_code(link["Type"].as_i64().unwrap_or(-1)),
+            target,
+        })
+    }
+}
+
+/// Collects the entries of all objects of an `ls` response.
+pub(crate) fn parse_objects(resp: &Value) -> Result<Vec<LsEntry>> {
+    let objects = resp["Objects"]
+        .as_array()
+        .ok_or_else(|| Error::response("Objects is not an array"))?;
+
+    let mut entries = Vec::new();
+    for object in objects {
+        let links = object["Links"]
+            .as_array()
+            .ok_or_else(|| Error::response("Links is not an array"))?;
+
+        for link in links {
+            entries.push(LsEntry::from_json(link)?);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// An iterator over the entries of a directory, returned by
+/// [`IpfsApi::ls_stream`]. The iterator ends after the first error.
+pub struct LsStream {
+    lines: JsonLines<Box<dyn Read + Send + Sync>>,
+    pending: VecDeque<LsEntry>,
+    failed: bool,
+}
+
+impl Iterator for LsStream {
+    type Item = Result<LsEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.pending.pop_front() {
+                return Some(Ok(entry));
+            }
+            if self.failed {
+                return None;
+            }
+
+            let entries =
+                self.lines.next()?.and_then(|line| parse_objects(&line));
+            match entries {
+                Ok(entries) => self.pending.extend(entries),
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl Debug for LsStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LsStream")
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
+
+impl IpfsApi {
+    /// Lists the entries of a UnixFS directory.
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// for entry in api.ls("QmS4ustL54uo8FzR9455qaxZwuMiUhyvMcX9Ba8nUH4uVv")? {
+    ///     println!("{} {} {}", entry.hash, entry.size, entry.name);
+    /// }
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/ls` API.
+    pub fn ls(&self, path: impl Into<String>) -> Result<Vec<LsEntry>> {
+        let resp = self.post("ls").query("arg", &path.into()).call()?;
+        let resp: Value = serde_json::from_reader(resp.into_reader())?;
+
+        parse_objects(&resp)
+    }
+
+    /// Lists the entries of a UnixFS directory as they are received, so
+    /// that large directories do not have to be held in memory.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/ls` API with `stream=true`.
+    pub fn ls_stream(&self, path: impl Into<String>) -> Result<LsStream> {
+        let resp = self
+            .post("ls")
+            .query("arg", &path.into())
+            .query("stream", "true")
+            .call()?;
+
+        Ok(LsStream {
+            lines: resp.into_json_lines(),
+            pending: VecDeque::new(),
+            failed: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileType;
+    use crate::cid::{self, Cid, Multihash};
+    use crate::testing::{MockResponse, MockServer};
+    use crate::Error;
+    use serde_json::json;
+
+    fn cid(name: &str) -> Cid {
+        Cid::new_v1(cid::DAG_PB, Multihash::sha2_256(name.as_bytes()))
+    }
+
+    fn link(
+        name: &str,
+        size: u64,
+        kind: i64,
+        target: &str,
+    ) -> serde_json::Value {
+        json!({
+            "Name": name,
+            "Hash": cid(name),
+            "Size": size,
+            "Type": kind,
+            "Target": target,
+        })
+    }
+
+    #[test]
+    fn test_ls() {
+        let server = MockServer::start();
+        server.on(
+            "ls",
+            MockResponse::json(&json!({
+                "Objects": [{
+                    "Hash": "QmDir",
+                    "Links": [
+                        link("index.html", 1024, 2, ""),
+                        link("assets", 0, 1, ""),
+                        link("home.html", 10, 4, "index.html"),
+                    ],
+                }],
+            })),
+        );
+
+        let entries = server.api().ls("QmDir").unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].name, "index.html");
+        assert_eq!(entries[0].hash, cid("index.html"));
+        assert_eq!(entries[0].size, 1024);
+        assert_eq!(entries[0].file_type, FileType::File);
+        assert_eq!(entries[1].file_type, FileType::Directory);
+        assert_eq!(entries[2].file_type, FileType::Symlink);
+        assert_eq!(entries[2].target.as_deref(), Some("index.html"));
+        assert_eq!(entries[0].target, None);
+    }
+
+    #[test]
+    fn test_ls_stream() {
+        let server = MockServer::start();
+        server.on(
+            "ls",
+            MockResponse::ndjson([
+                json!({"Objects": [{"Hash": "QmDir", "Links": [link("a", 1, 2, "")]}]}),
+                json!({"Objects": [{"Hash": "QmDir", "Links": [link("b", 2, 2, "")]}]}),
+            ])
+            .stream_error("context canceled"),
+        );
+
+        let mut stream = server.api().ls_stream("QmDir").unwrap();
+        assert_eq!(stream.next().unwrap().unwrap().name, "a");
+        assert_eq!(stream.next().unwrap().unwrap().name, "b");
+        assert!(matches!(stream.next(), Some(Err(Error::Stream(_)))));
+        assert!(stream.next().is_none());
+
+        let request = &server.requests()[0];
+        assert_eq!(request.query_value("stream"), Some("true"));
+    }
+}
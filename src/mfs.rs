@@ -0,0 +1,856 @@
+//! The Mutable File System (MFS), a file system that the daemon keeps on top
+//! of IPFS.
+//!
+//! The MFS is accessed through the [`Mfs`] handle returned by
+//! [`IpfsApi::mfs`].
+//!
+//! ```rust,no_run
+//! use ipfsapi::mfs::WriteOptions;
+//! use ipfsapi::IpfsApi;
+//!
+//! let api = IpfsApi::new("127.0.0.1", 5001);
+//! let mfs = api.mfs();
+//!
+//! mfs.mkdir("/notes", true)?;
+//! let opts = WriteOptions::new().create(true).truncate(true);
+//! mfs.write("/notes/todo.txt", &b"Buy milk"[..], &opts)?;
+//!
+//! let stat = mfs.stat("/notes")?;
+//! println!("{} {}", stat.hash, stat.cumulative_size);
+//! # Ok::<(), ipfsapi::Error>(())
+//! ```
+
+use crate::add::AddOptions;
+use crate::cid::{Cid, Version};
+use crate::ls::FileType;
+use crate::multipart::{Multipart, Part};
+use crate::{ByteStream, Error, IpfsApi, Result};
+use serde_json::Value;
+use std::fmt::Debug;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// The number of bytes that [`MfsFile`] buffers before writing them.
+const WRITE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// The number of bytes that [`MfsFile`] fetches at a time when reading.
+const READ_CHUNK_SIZE: u64 = 256 * 1024;
+
+/// A handle on the MFS of the daemon, returned by [`IpfsApi::mfs`].
+#[derive(Clone, Copy, Debug)]
+pub struct Mfs<'a> {
+    pub(crate) api: &'a IpfsApi,
+}
+
+/// Options of [`Mfs::write`].
+///
+/// Options that are not set are left to the defaults of the daemon, which
+/// writes at the start of an existing file without truncating it.
+#[derive(Clone, Debug, Default)]
+pub struct WriteOptions {
+    offset: Option<u64>,
+    count: Option<u64>,
+    create: bool,
+    parents: bool,
+    truncate: bool,
+    raw_leaves: Option<bool>,
+    cid_version: Option<Version>,
+}
+
+impl WriteOptions {
+    /// Creates options that use the defaults of the daemon.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the byte offset at which to start writing.
+    #[must_use]
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Sets the maximum number of bytes to write.
+    #[must_use]
+    pub fn count(mut self, count: u64) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Creates the file if it does not exist.
+    #[must_use]
+    pub fn create(mut self, create: bool) -> Self {
+        self.create = create;
+        self
+    }
+
+    /// Creates the parent directories if they do not exist.
+    #[must_use]
+    pub fn parents(mut self, parents: bool) -> Self {
+        self.parents = parents;
+        self
+    }
+
+    /// Truncates the file before writing.
+    #[must_use]
+    pub fn truncate(mut self, truncate: bool) -> Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Stores the leaves of new blocks as raw blocks.
+    #[must_use]
+    pub fn raw_leaves(mut self, raw_leaves: bool) -> Self {
+        self.raw_leaves = Some(raw_leaves);
+        self
+    }
+
+    /// Sets the CID version of new blocks.
+    #[must_use]
+    pub fn cid_version(mut self, version: Version) -> Self {
+        self.cid_version = Some(version);
+        self
+    }
+}
+
+/// Options of [`Mfs::sync`].
+///
+/// ```rust
+/// use ipfsapi::add::AddOptions;
+/// use ipfsapi::cid::Version;
+/// use ipfsapi::mfs::SyncOptions;
+///
+/// let add = AddOptions::new().cid_version(Version::V1).ignore("target");
+/// let opts = SyncOptions::new().add_options(add).dry_run(true);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SyncOptions {
+    pub(crate) dry_run: bool,
+    pub(crate) add: AddOptions,
+}
+
+impl SyncOptions {
+    /// Creates options that apply the changes with the default add options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only computes the changes, without applying them.
+    #[must_use]
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Sets the options used to compute the CIDs of the local files and to
+    /// upload them. Their `hidden`, `ignore` and `follow_symlinks` filters
+    /// select the local entries.
+    #[must_use]
+    pub fn add_options(mut self, add: AddOptions) -> Self {
+        self.add = add;
+        self
+    }
+}
+
+/// The changes made by [`Mfs::sync`], as paths relative to the synchronised
+/// directories.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// The files and directories that did not exist in the MFS.
+    pub added: Vec<String>,
+
+    /// The files whose contents differ, and the entries that changed
+    /// between a file and a directory.
+    pub modified: Vec<String>,
+
+    /// The files and directories that no longer exist locally. The entries
+    /// inside removed directories are not listed.
+    pub removed: Vec<String>,
+
+    /// The number of files that did not change.
+    pub unchanged: usize,
+}
+
+/// An entry of a directory listed by [`Mfs::ls`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MfsEntry {
+    /// The name of the entry in the directory.
+    pub name: String,
+
+    /// The type of the entry.
+    pub file_type: FileType,
+
+    /// The size of the file, or 0 for directories.
+    pub size: u64,
+
+    /// The CID of the entry.
+    pub hash: Cid,
+}
+
+/// The status of a file or directory, returned by [`Mfs::stat`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MfsStat {
+    /// The CID of the entry.
+    pub hash: Cid,
+
+    /// The size of the file, or 0 for directories.
+    pub size: u64,
+
+    /// The size of the entry's DAG, including all of its blocks.
+    pub cumulative_size: u64,
+
+    /// The number of links of the entry's root block.
+    pub blocks: u64,
+
+    /// The type of the entry.
+    pub file_type: FileType,
+}
+
+/// Options of [`Mfs::open`].
+///
+/// ```rust
+/// use ipfsapi::mfs::OpenOptions;
+///
+/// let opts = OpenOptions::new().create(true).truncate(true);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OpenOptions {
+    create: bool,
+    truncate: bool,
+    parents: bool,
+}
+
+impl OpenOptions {
+    /// Creates options that open an existing file without changing it.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the file if it does not exist.
+    #[must_use]
+    pub fn create(mut self, create: bool) -> Self {
+        self.create = create;
+        self
+    }
+
+    /// Truncates the file when it is opened.
+    #[must_use]
+    pub fn truncate(mut self, truncate: bool) -> Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Creates the parent directories of a new file if they do not exist.
+    #[must_use]
+    pub fn parents(mut self, parents: bool) -> Self {
+        self.parents = parents;
+        self
+    }
+}
+
+/// A file of the MFS, opened with [`Mfs::open`].
+///
+/// Writes are buffered and sent with `files/write` at the position of the
+/// handle, and reads fetch ranges of the file with `files/read`. Buffered
+/// writes are sent when the handle is flushed, read from, seeked or dropped.
+/// Errors are ignored when the handle is dropped, so call
+/// [`flush`](Write::flush) to handle them.
+pub struct MfsFile<'a> {
+    mfs: Mfs<'a>,
+    path: String,
+    pos: u64,
+    write_buf: Vec<u8>,
+    read_buf: Vec<u8>,
+    read_pos: usize,
+}
+
+impl MfsFile<'_> {
+    /// Sends the buffered writes, which end at the current position.
+    fn flush_buf(&mut self) -> Result<()> {
+        if self.write_buf.is_empty() {
+            return Ok(());
+        }
+
+        let offset = self.pos - self.write_buf.len() as u64;
+        let opts = WriteOptions::new().offset(offset);
+        self.mfs.write(&self.path, &self.write_buf[..], &opts)?;
+        self.write_buf.clear();
+        Ok(())
+    }
+
+    fn clear_read_buf(&mut self) {
+        self.read_buf.clear();
+        self.read_pos = 0;
+    }
+}
+
+impl Write for MfsFile<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.clear_read_buf();
+        if self.write_buf.len() + buf.len() > WRITE_BUFFER_SIZE {
+            self.flush_buf()?;
+        }
+
+        self.write_buf.extend_from_slice(buf);
+        self.pos += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(self.flush_buf()?)
+    }
+}
+
+impl Read for MfsFile<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.flush_buf()?;
+        if self.read_pos == self.read_buf.len() {
+            self.clear_read_buf();
+            let count = READ_CHUNK_SIZE.max(buf.len() as u64);
+            self.mfs
+                .read(&self.path, self.pos, Some(count))?
+                .read_to_end(&mut self.read_buf)?;
+        }
+
+        let n = (&self.read_buf[self.read_pos..]).read(buf)?;
+        self.read_pos += n;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for MfsFile<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.flush_buf()?;
+
+        let (base, offset) = match pos {
+            SeekFrom::Start(pos) => (pos, 0),
+            SeekFrom::Current(offset) => (self.pos, offset),
+            SeekFrom::End(offset) => (self.mfs.stat(&self.path)?.size, offset),
+        };
+        let pos = base.checked_add_signed(offset).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        if pos != self.pos {
+            self.clear_read_buf();
+            self.pos = pos;
+        }
+        Ok(pos)
+    }
+}
+
+impl Drop for MfsFile<'_> {
+    fn drop(&mut self) {
+        let _ = self.flush_buf();
+    }
+}
+
+impl Debug for MfsFile<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MfsFile")
+            .field("path", &self.path)
+            .field("pos", &self.pos)
+            .finish_non_exhaustive()
+    }
+}
+
+fn string(val: &Value, field: &str) -> Result<String> {
+    val[field]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| Error::response(&format!("{} is not a string", field)))
+}
+
+fn number(val: &Value, field: &str) -> Result<u64> {
+    val[field]
+        .as_u64()
+        .ok_or_else(|| Error::response(&format!("{} is not a number", field)))
+}
+
+impl MfsEntry {
+    fn from_json(val: &Value) -> Result<Self> {
+        // The MFS uses its own type codes, where 1 is a directory.
+        let file_type = match val["Type"].as_i64() {
+            Some(0) => FileType::File,
+            Some(1) => FileType::Directory,
+            other => FileType::Other(other.unwrap_or(-1)),
+        };
+
+        Ok(Self {
+            name: string(val, "Name")?,
+            file_type,
+            size: number(val, "Size")?,
+            hash: string(val, "Hash")?.parse()?,
+        })
+    }
+}
+
+impl MfsStat {
+    fn from_json(val: &Value) -> Result<Self> {
+        let file_type = match val["Type"].as_str() {
+            Some("file") => FileType::File,
+            Some("directory") => FileType::Directory,
+            Some("symlink") => FileType::Symlink,
+            _ => FileType::Other(-1),
+        };
+
+        Ok(Self {
+            hash: string(val, "Hash")?.parse()?,
+            size: number(val, "Size")?,
+            cumulative_size: number(val, "CumulativeSize")?,
+            blocks: number(val, "Blocks")?,
+            file_type,
+        })
+    }
+}
+
+impl<'a> Mfs<'a> {
+    /// Opens a file for reading and writing.
+    ///
+    /// Without `create` or `truncate`, the file must already exist. The
+    /// handle starts at the beginning of the file.
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// use ipfsapi::mfs::OpenOptions;
+    ///
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let opts = OpenOptions::new().create(true).truncate(true);
+    /// let mut file = api.mfs().open("/config.json", &opts)?;
+    /// serde_json::to_writer(&mut file, &serde_json::json!({"retries": 3}))?;
+    /// std::io::Write::flush(&mut file)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn open(&self, path: &str, opts: &OpenOptions) -> Result<MfsFile<'a>> {
+        if opts.create || opts.truncate {
+            let write = WriteOptions::new()
+                .create(opts.create)
+                .parents(opts.parents)
+                .truncate(opts.truncate);
+            self.write(path, std::io::empty(), &write)?;
+        } else if self.stat(path)?.file_type != FileType::File {
+            return Err(Error::InvalidInput(format!("{} is not a file", path)));
+        }
+
+        Ok(MfsFile {
+            mfs: *self,
+            path: path.into(),
+            pos: 0,
+            write_buf: Vec::new(),
+            read_buf: Vec::new(),
+            read_pos: 0,
+        })
+    }
+
+    /// Creates a directory. With `parents`, the missing parent directories
+    /// are created too, and it is not an error if the directory exists.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/files/mkdir` API.
+    pub fn mkdir(&self, path: &str, parents: bool) -> Result<()> {
+        self.api
+            .post("files/mkdir")
+            .query("arg", path)
+            .query("parents", &parents.to_string())
+            .call()?;
+        Ok(())
+    }
+
+    /// Writes to a file, streaming the contents from a reader.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/files/write` API.
+    pub fn write(
+        &self,
+        path: &str,
+        data: impl Read + Send,
+        opts: &WriteOptions,
+    ) -> Result<()> {
+        crate::rand::feed_event("files_write");
+
+        let mut body = Multipart::new();
+        body.add_part(
+            Part::new("data", data)
+                .filename("")
+                .header("Content-Type", "application/octet-stream"),
+        );
+
+        let mut req = self
+            .api
+            .post("files/write")
+            .set("Content-Type", &body.content_type())
+            .query("arg", path)
+            .query("create", &opts.create.to_string())
+            .query("parents", &opts.parents.to_string())
+            .query("truncate", &opts.truncate.to_string());
+        if let Some(offset) = opts.offset {
+            req = req.query("offset", &offset.to_string());
+        }
+        if let Some(count) = opts.count {
+            req = req.query("count", &count.to_string());
+        }
+        if let Some(raw_leaves) = opts.raw_leaves {
+            req = req.query("raw-leaves", &raw_leaves.to_string());
+        }
+        if let Some(version) = opts.cid_version {
+            req = req.query("cid-version", version.as_arg());
+        }
+
+        req.send(body.into_reader())?;
+        Ok(())
+    }
+
+    /// Reads a file, starting at `offset`. At most `count` bytes are read if
+    /// it is set.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/files/read` API.
+    pub fn read(
+        &self,
+        path: &str,
+        offset: u64,
+        count: Option<u64>,
+    ) -> Result<ByteStream> {
+        let mut req = self.api.post("files/read").query("arg", path);
+        if offset > 0 {
+            req = req.query("offset", &offset.to_string());
+        }
+        if let Some(count) = count {
+            req = req.query("count", &count.to_string());
+        }
+
+        Ok(req.call()?.into_stream())
+    }
+
+    /// Lists the entries of a directory. Listing a file returns the file
+    /// itself.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/files/ls` API.
+    pub fn ls(&self, path: &str) -> Result<Vec<MfsEntry>> {
+        let resp = self
+            .api
+            .post("files/ls")
+            .query("arg", path)
+            .query("long", "true")
+            .call()?;
+        let resp: Value = serde_json::from_reader(resp.into_reader())?;
+
+        // The entries of an empty directory are null.
+        match &resp["Entries"] {
+            Value::Null => Ok(Vec::new()),
+            Value::Array(entries) => {
+                entries.iter().map(MfsEntry::from_json).collect()
+            }
+            _ => Err(Error::response("Entries is not an array")),
+        }
+    }
+
+    /// Returns the status of a file or directory.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/files/stat` API.
+    pub fn stat(&self, path: &str) -> Result<MfsStat> {
+        let resp = self.api.post("files/stat").query("arg", path).call()?;
+        let resp: Value = serde_json::from_reader(resp.into_reader())?;
+
+        MfsStat::from_json(&resp)
+    }
+
+    /// Moves a file or directory.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/files/mv` API.
+    pub fn mv(&self, source: &str, dest: &str) -> Result<()> {
+        self.api
+            .post("files/mv")
+            .query("arg", source)
+            .query("arg", dest)
+            .call()?;
+        Ok(())
+    }
+
+    /// Copies a file or directory. The source can be an MFS path, or an IPFS
+    /// path such as `/ipfs/<cid>`.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/files/cp` API.
+    pub fn cp(&self, source: &str, dest: &str) -> Result<()> {
+        self.api
+            .post("files/cp")
+            .query("arg", source)
+            .query("arg", dest)
+            .call()?;
+        Ok(())
+    }
+
+    /// Removes a file or directory. Directories can only be removed with
+    /// `recursive`, and `force` removes the entry even if its contents
+    /// cannot be fetched.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/files/rm` API.
+    pub fn rm(&self, path: &str, recursive: bool, force: bool) -> Result<()> {
+        self.api
+            .post("files/rm")
+            .query("arg", path)
+            .query("recursive", &recursive.to_string())
+            .query("force", &force.to_string())
+            .call()?;
+        Ok(())
+    }
+
+    /// Writes the changes under a path to the repository, and returns the
+    /// CID of the path.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/files/flush` API.
+    pub fn flush(&self, path: &str) -> Result<Cid> {
+        let resp = self.api.post("files/flush").query("arg", path).call()?;
+        let resp: Value = serde_json::from_reader(resp.into_reader())?;
+
+        string(&resp, "Cid")?.parse()
+    }
+
+    /// Changes the CID version of a file or directory, and of everything
+    /// under it.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/files/chcid` API.
+    pub fn chcid(&self, path: &str, cid_version: Version) -> Result<()> {
+        self.api
+            .post("files/chcid")
+            .query("arg", path)
+            .query("cid-version", cid_version.as_arg())
+            .call()?;
+        Ok(())
+    }
+}
+
+impl IpfsApi {
+    /// Returns a handle on the Mutable File System (MFS) of the daemon.
+    #[must_use]
+    pub fn mfs(&self) -> Mfs<'_> {
+        Mfs { api: self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OpenOptions, WriteOptions};
+    use crate::cid::Version;
+    use crate::ls::FileType;
+    use crate::testing::{MockResponse, MockServer};
+    use crate::Error;
+    use serde_json::json;
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    #[test]
+    fn test_write_and_read() {
+        let server = MockServer::start();
+        server.on("files/write", MockResponse::new(200, ""));
+        server.on("files/read", MockResponse::new(200, "world"));
+
+        let api = server.api();
+        let opts = WriteOptions::new()
+            .create(true)
+            .parents(true)
+            .offset(6)
+            .cid_version(Version::V1);
+        api.mfs()
+            .write("/dir/hello.txt", &b"world"[..], &opts)
+            .unwrap();
+
+        let mut read = String::new();
+        api.mfs()
+            .read("/dir/hello.txt", 6, Some(5))
+            .unwrap()
+            .read_to_string(&mut read)
+            .unwrap();
+        assert_eq!(read, "world");
+
+        let requests = server.requests();
+        assert_eq!(requests[0].args(), ["/dir/hello.txt"]);
+        assert_eq!(requests[0].query_value("create"), Some("true"));
+        assert_eq!(requests[0].query_value("parents"), Some("true"));
+        assert_eq!(requests[0].query_value("truncate"), Some("false"));
+        assert_eq!(requests[0].query_value("offset"), Some("6"));
+        assert_eq!(requests[0].query_value("cid-version"), Some("1"));
+        assert_eq!(requests[0].parts().unwrap()[0].body, b"world");
+        assert_eq!(requests[1].query_value("offset"), Some("6"));
+        assert_eq!(requests[1].query_value("count"), Some("5"));
+    }
+
+    #[test]
+    fn test_ls_and_stat() {
+        let a = "QmbyV4BASLJCFiCfKz37eNwFX8y6hefQ7LGpzvEwJQzNft";
+        let sub = "QmdGNhud6dAKjpYUj2vyXxPzznQ94cbihvBQ9gXEfxYxwj";
+        let dir = "QmRG3NJGREHD66UmwcaNoFVMKAqVXSjY28LZc9qcRxxrCT";
+        let server = MockServer::start();
+        server.on(
+            "files/ls",
+            MockResponse::json(&json!({
+                "Entries": [
+                    {"Name": "a.txt", "Type": 0, "Size": 5, "Hash": a},
+                    {"Name": "sub", "Type": 1, "Size": 0, "Hash": sub},
+                ],
+            })),
+        );
+        server.on(
+            "files/stat",
+            MockResponse::json(&json!({
+                "Hash": dir,
+                "Size": 0,
+                "CumulativeSize": 120,
+                "Blocks": 2,
+                "Type": "directory",
+            })),
+        );
+
+        let api = server.api();
+        let mfs = api.mfs();
+        let entries = mfs.ls("/dir").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].file_type, FileType::File);
+        assert_eq!(entries[0].size, 5);
+        assert_eq!(entries[1].file_type, FileType::Directory);
+        assert_eq!(entries[1].hash.to_string(), sub);
+
+        let stat = mfs.stat("/dir").unwrap();
+        assert_eq!(stat.hash.to_string(), dir);
+        assert_eq!(stat.cumulative_size, 120);
+        assert_eq!(stat.blocks, 2);
+        assert_eq!(stat.file_type, FileType::Directory);
+
+        let result = mfs.open("/dir", &OpenOptions::new());
+        match result {
+            Err(Error::InvalidInput(msg)) => {
+                assert_eq!(msg, "/dir is not a file")
+            }
+            _ => panic!("a directory was opened as a file"),
+        }
+    }
+
+    #[test]
+    fn test_ls_empty() {
+        let server = MockServer::start();
+        server.on("files/ls", MockResponse::json(&json!({"Entries": null})));
+
+        assert!(server.api().mfs().ls("/").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_changes() {
+        let server = MockServer::start();
+        server.on("files/flush", MockResponse::json(&json!({"Cid": "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u"})));
+
+        for endpoint in ["mkdir", "cp", "mv", "rm", "chcid"] {
+            server
+                .on(&format!("files/{}", endpoint), MockResponse::new(200, ""));
+        }
+
+        let api = server.api();
+        let mfs = api.mfs();
+        mfs.mkdir("/a/b", true).unwrap();
+        mfs.cp("/ipfs/QmFile", "/a/b/file").unwrap();
+        mfs.mv("/a/b", "/c").unwrap();
+        mfs.rm("/c", true, false).unwrap();
+        mfs.chcid("/", Version::V1).unwrap();
+        assert_eq!(
+            mfs.flush("/").unwrap().to_string(),
+            "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u"
+        );
+
+        let requests: Vec<String> = server
+            .requests()
+            .iter()
+            .map(|r| {
+                let query: Vec<String> = r
+                    .query
+                    .iter()
+                    .map(|(n, v)| format!("{}={}", n, v))
+                    .collect();
+                format!("{} {}", r.endpoint, query.join("&"))
+            })
+            .collect();
+        assert_eq!(
+            requests,
+            [
+                "files/mkdir arg=/a/b&parents=true",
+                "files/cp arg=/ipfs/QmFile&arg=/a/b/file",
+                "files/mv arg=/a/b&arg=/c",
+                "files/rm arg=/c&recursive=true&force=false",
+                "files/chcid arg=/&cid-version=1",
+                "files/flush arg=/",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_write() {
+        let server = MockServer::start();
+        server.on("files/write", MockResponse::new(200, ""));
+
+        let api = server.api();
+        let opts = OpenOptions::new().create(true).truncate(true);
+        let mut file = api.mfs().open("/log.txt", &opts).unwrap();
+        file.write_all(b"hello ").unwrap();
+        file.write_all(b"world").unwrap();
+        file.flush().unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.write_all(b"H").unwrap();
+        drop(file);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].query_value("create"), Some("true"));
+        assert_eq!(requests[0].query_value("truncate"), Some("true"));
+        assert_eq!(requests[0].parts().unwrap()[0].body, b"");
+        assert_eq!(requests[1].query_value("create"), Some("false"));
+        assert_eq!(requests[1].query_value("offset"), Some("0"));
+        assert_eq!(requests[1].parts().unwrap()[0].body, b"hello world");
+        assert_eq!(requests[2].query_value("offset"), Some("0"));
+        assert_eq!(requests[2].parts().unwrap()[0].body, b"H");
+    }
+
+    #[test]
+    fn test_file_read_and_seek() {
+        let server = MockServer::start();
+        server.on(
+            "files/stat",
+            MockResponse::json(&json!({
+                "Hash": "QmSMLv5SLHWv4iusKXe9hGR5Ci6eskkrFsfkUgyE2kz9kj",
+                "Size": 11,
+                "CumulativeSize": 19,
+                "Blocks": 0,
+                "Type": "file",
+            })),
+        );
+        server.on("files/read", MockResponse::new(200, "world"));
+        server.on("files/read", MockResponse::new(200, ""));
+
+        let api = server.api();
+        let mut file = api.mfs().open("/log.txt", &OpenOptions::new()).unwrap();
+        assert_eq!(file.seek(SeekFrom::End(-5)).unwrap(), 6);
+        let mut read = String::new();
+        file.read_to_string(&mut read).unwrap();
+        assert_eq!(read, "world");
+        assert_eq!(file.stream_position().unwrap(), 11);
+        assert!(file.seek(SeekFrom::Current(-12)).is_err());
+
+        let requests = server.requests();
+        let reads: Vec<_> = requests
+            .iter()
+            .filter(|r| r.endpoint == "files/read")
+            .map(|r| r.query_value("offset"))
+            .collect();
+        assert_eq!(reads, [Some("6"), Some("11")]);
+    }
+}
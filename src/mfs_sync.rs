@@ -0,0 +1,488 @@
+use crate::add::AddOptions;
+use crate::add_path::{local_entries, Entry};
+use crate::cid::Cid;
+use crate::ls::FileType;
+use crate::mfs::{Mfs, SyncOptions, SyncReport};
+use crate::unixfs;
+use crate::{Error, Result};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// An entry of the MFS directory being synchronised.
+struct Remote {
+    is_dir: bool,
+    size: u64,
+    hash: Cid,
+}
+
+/// A change to apply to the MFS, with paths relative to the synchronised
+/// directory.
+enum Action {
+    Remove(String),
+    Mkdir(String),
+    Upload(String, PathBuf),
+    /// Uploads a file, then replaces the MFS entry with it.
+    Replace(String, PathBuf),
+}
+
+/// Returns whether `rel` or one of its parents is in `paths`.
+fn under(paths: &HashSet<String>, rel: &str) -> bool {
+    rel.match_indices('/')
+        .map(|(i, _)| &rel[..i])
+        .chain(std::iter::once(rel))
+        .any(|parent| paths.contains(parent))
+}
+
+/// The message of the error returned by the MFS for a path that does not
+/// exist. Kubo reports it with the generic error code, so it can only be
+/// told apart by its message.
+const NOT_FOUND_MESSAGE: &str = "file does not exist";
+
+/// Returns whether the daemon reported that a path does not exist.
+fn is_missing(err: &Error) -> bool {
+    match err {
+        Error::Api { message, .. } => message.contains(NOT_FOUND_MESSAGE),
+        _ => false,
+    }
+}
+
+impl Mfs<'_> {
+    /// Collects the entries under an MFS directory, named by their path
+    /// relative to `root`.
+    fn remote_entries(&self, root: &str) -> Result<BTreeMap<String, Remote>> {
+        let mut entries = BTreeMap::new();
+        let mut dirs = vec![String::new()];
+
+        while let Some(dir) = dirs.pop() {
+            for entry in self.ls(&join(root, &dir))? {
+                let rel = join(&dir, &entry.name);
+                let is_dir = entry.file_type == FileType::Directory;
+                if is_dir {
+                    dirs.push(rel.clone());
+                }
+
+                let remote = Remote {
+                    is_dir,
+                    size: entry.size,
+                    hash: entry.hash,
+                };
+                entries.insert(rel, remote);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Adds a local file and returns its CID.
+    fn add_file(&self, path: &Path, opts: &AddOptions) -> Result<Cid> {
+        let added = self.api.add(File::open(path)?, opts)?;
+        added
+            .last()
+            .map(|added| added.hash.clone())
+            .ok_or_else(|| Error::response("no entries were added"))
+    }
+
+    /// Returns whether a local file has the same contents as an MFS file.
+    /// The CID of the local file is computed offline when the importer
+    /// supports the add options, and by the daemon otherwise.
+    fn same_file(
+        &self,
+        path: &Path,
+        remote: &Remote,
+        opts: &AddOptions,
+    ) -> Result<bool> {
+        if std::fs::metadata(path)?.len() != remote.size {
+            return Ok(false);
+        }
+
+        let hash = match opts.import_options() {
+            Some(import) => unixfs::hash(File::open(path)?, &import)?,
+            None => self.add_file(path, &opts.clone().only_hash(true))?,
+        };
+        Ok(hash == remote.hash)
+    }
+
+    /// Mirrors the local directory `local` into the MFS directory `path`.
+    /// New and modified files are uploaded, and the entries that no longer
+    /// exist locally are removed. The MFS directory is created if it does
+    /// not exist.
+    ///
+    /// Files with different sizes are modified. Files with the same size are
+    /// compared by their CID, which is computed with the add options of
+    /// `opts`, so the same options should be used for every
+    /// synchronisation. The CID is computed locally with the [`unixfs`]
+    /// importer, unless the options use a chunker other than `size-<n>`, a
+    /// hash function other than SHA2-256, inlining or the trickle layout:
+    /// the daemon then computes it without storing the file. Options that
+    /// are not set use the defaults of Kubo, which should be set explicitly
+    /// if the daemon's `Import` configuration differs.
+    ///
+    /// Uploaded files are not pinned, as the MFS keeps them. A modified file
+    /// is only removed from the MFS once its new contents have been added,
+    /// so a failed upload leaves the previous version in place.
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// use ipfsapi::mfs::SyncOptions;
+    ///
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let report = api.mfs().sync("public", "/www", &SyncOptions::new())?;
+    /// for path in report.added.iter().chain(&report.modified) {
+    ///     println!("uploaded {}", path);
+    /// }
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    ///
+    /// # Notes
+    /// This uses the `/api/v0/add` and `/api/v0/files/*` APIs.
+    pub fn sync(
+        &self,
+        local: impl AsRef<Path>,
+        path: &str,
+        opts: &SyncOptions,
+    ) -> Result<SyncReport> {
+        crate::rand::feed_event("mfs_sync");
+
+        let mut add = opts.add.clone().pin(false).only_hash(false);
+        add.wrap_with_directory = None;
+        add.to_files = None;
+
+        let locals = local_entries(local.as_ref(), &opts.add)?;
+        let (exists, remotes) = match self.stat(path) {
+            Ok(stat) if stat.file_type == FileType::Directory => {
+                (true, self.remote_entries(path)?)
+            }
+            Ok(_) => {
+                let msg = format!("{} is not a directory", path);
+                return Err(Error::InvalidInput(msg));
+            }
+            Err(err) if is_missing(&err) => (false, BTreeMap::new()),
+            Err(err) => return Err(err),
+        };
+
+        // Symbolic links are not synchronised.
+        let locals: Vec<(String, Entry)> = locals
+            .into_iter()
+            .filter(|(_, entry)| !matches!(entry, Entry::Symlink(_)))
+            .collect();
+        let local_dirs: HashMap<&str, bool> = locals
+            .iter()
+            .map(|(rel, entry)| {
+                (rel.as_str(), matches!(entry, Entry::Directory))
+            })
+            .collect();
+
+        let mut report = SyncReport::default();
+        let mut actions = Vec::new();
+
+        // The entries under a removed or replaced directory go with it.
+        let mut gone = HashSet::new();
+        for (rel, remote) in &remotes {
+            if under(&gone, rel) {
+                continue;
+            }
+
+            match local_dirs.get(rel.as_str()) {
+                Some(&is_dir) if is_dir == remote.is_dir => {}
+                Some(_) => {
+                    gone.insert(rel.clone());
+                }
+                None => {
+                    report.removed.push(rel.clone());
+                    actions.push(Action::Remove(rel.clone()));
+                    gone.insert(rel.clone());
+                }
+            }
+        }
+
+        for (rel, entry) in &locals {
+            let remote = remotes.get(rel);
+            let replaced = remote.is_some() && gone.contains(rel);
+
+            match (entry, remote) {
+                (Entry::Directory, Some(_)) if !replaced => continue,
+                (Entry::Directory, _) => {
+                    if replaced {
+                        actions.push(Action::Remove(rel.clone()));
+                    }
+                    actions.push(Action::Mkdir(rel.clone()));
+                }
+                (Entry::File(file), Some(remote)) => {
+                    if !replaced && self.same_file(file, remote, &add)? {
+                        report.unchanged += 1;
+                        continue;
+                    }
+                    actions.push(Action::Replace(rel.clone(), file.clone()));
+                }
+                (Entry::File(file), None) => {
+                    actions.push(Action::Upload(rel.clone(), file.clone()));
+                }
+                (Entry::Symlink(_), _) => continue,
+            }
+
+            match remote {
+                Some(_) => report.modified.push(rel.clone()),
+                None => report.added.push(rel.clone()),
+            }
+        }
+
+        if opts.dry_run {
+            return Ok(report);
+        }
+
+        if !exists {
+            self.mkdir(path, true)?;
+        }
+        for action in actions {
+            match action {
+                Action::Remove(rel) => {
+                    self.rm(&join(path, &rel), true, true)?
+                }
+                Action::Mkdir(rel) => self.mkdir(&join(path, &rel), false)?,
+                Action::Upload(rel, file) => {
+                    let hash = self.add_file(&file, &add)?;
+                    self.cp(&format!("/ipfs/{}", hash), &join(path, &rel))?;
+                }
+                Action::Replace(rel, file) => {
+                    let hash = self.add_file(&file, &add)?;
+                    let dest = join(path, &rel);
+                    self.rm(&dest, true, true)?;
+                    self.cp(&format!("/ipfs/{}", hash), &dest)?;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Joins a relative path to a directory, where an empty path is the
+/// directory itself.
+fn join(dir: &str, rel: &str) -> String {
+    match (dir, rel) {
+        (dir, "") => dir.into(),
+        ("", rel) => rel.into(),
+        (dir, rel) => format!("{}/{}", dir.trim_end_matches('/'), rel),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NOT_FOUND_MESSAGE;
+    use crate::add::AddOptions;
+    use crate::mfs::SyncOptions;
+    use crate::testing::{MockResponse, MockServer};
+    use crate::Error;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn local_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ipfsapi-sync-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("changed.txt"), "hello").unwrap();
+        std::fs::write(dir.join("same.txt"), "same").unwrap();
+        std::fs::write(dir.join("sub/new.txt"), "new").unwrap();
+        std::fs::write(dir.join(".hidden"), "hidden").unwrap();
+        dir
+    }
+
+    const OLD: &str = "Qmc3XM8LVuJRzJxqzDtCoVGcfhU2CmvmeJpiGxypfB7MNs";
+    const NEW: &str = "QmPWEpC8ikaHVXCGDuJ8ztDGBbJDL3QxMXmekjCZ4vwSog";
+
+    /// The CID of `same.txt` with the default add options.
+    const SAME: &str = "QmRHar2dvsZtjdnTYMnuoY8RVZ3BEy4ot6oaCWhZzu9ZdB";
+
+    fn entry(
+        name: &str,
+        kind: i64,
+        size: u64,
+        hash: &str,
+    ) -> serde_json::Value {
+        json!({"Name": name, "Type": kind, "Size": size, "Hash": hash})
+    }
+
+    fn server() -> MockServer {
+        let added = json!({"Name": "", "Hash": NEW, "Size": "3"});
+        server_with_add(MockResponse::json(&added))
+    }
+
+    fn server_with_add(add: MockResponse) -> MockServer {
+        let server = MockServer::start();
+        server.on(
+            "files/stat",
+            MockResponse::json(&json!({
+                "Hash": "QmTC17xJyj17U7RpYgSzwUvm6Q8zkemd7jcPrZyPxAeums",
+                "Size": 0,
+                "CumulativeSize": 100,
+                "Blocks": 4,
+                "Type": "directory",
+            })),
+        );
+        server.on(
+            "files/ls",
+            MockResponse::json(&json!({
+                "Entries": [
+                    entry("changed.txt", 0, 3, OLD),
+                    entry("gone", 1, 0, OLD),
+                    entry("same.txt", 0, 4, SAME),
+                    entry("sub", 0, 3, OLD),
+                ],
+            })),
+        );
+        server.on(
+            "files/ls",
+            MockResponse::json(&json!({"Entries": [entry("a", 0, 1, OLD)]})),
+        );
+        server.on("add", add);
+        for endpoint in ["files/rm", "files/mkdir", "files/cp"] {
+            server.on(endpoint, MockResponse::new(200, ""));
+        }
+        server
+    }
+
+    fn calls(server: &MockServer) -> Vec<String> {
+        server
+            .requests()
+            .iter()
+            .filter(|r| r.endpoint != "files/ls" && r.endpoint != "files/stat")
+            .map(|r| format!("{} {}", r.endpoint, r.args().join(" ")))
+            .collect()
+    }
+
+    #[test]
+    fn test_sync() {
+        let dir = local_dir("sync");
+
+        let dry_run = server();
+        let opts = SyncOptions::new().dry_run(true);
+        let planned = dry_run.api().mfs().sync(&dir, "/www", &opts);
+
+        let server = server();
+        let opts = SyncOptions::new();
+        let report = server.api().mfs().sync(&dir, "/www/", &opts);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let report = report.unwrap();
+        assert_eq!(planned.unwrap(), report);
+        assert_eq!(report.added, ["sub/new.txt"]);
+        assert_eq!(report.modified, ["changed.txt", "sub"]);
+        assert_eq!(report.removed, ["gone"]);
+        assert_eq!(report.unchanged, 1);
+
+        // The CID of same.txt is computed locally.
+        assert!(calls(&dry_run).is_empty());
+
+        assert_eq!(
+            calls(&server),
+            [
+                "files/rm /www/gone".into(),
+                "add ".into(),
+                "files/rm /www/changed.txt".into(),
+                format!("files/cp /ipfs/{} /www/changed.txt", NEW),
+                "files/rm /www/sub".into(),
+                "files/mkdir /www/sub".into(),
+                "add ".into(),
+                format!("files/cp /ipfs/{} /www/sub/new.txt", NEW),
+            ]
+        );
+        let requests = server.requests();
+        let upload = requests.iter().find(|r| r.endpoint == "add").unwrap();
+        assert_eq!(upload.query_value("only-hash"), Some("false"));
+        assert_eq!(upload.query_value("pin"), Some("false"));
+    }
+
+    #[test]
+    fn test_sync_failed_upload() {
+        let dir = local_dir("failed");
+
+        // The modified file is kept when its new contents cannot be added.
+        let server =
+            server_with_add(MockResponse::error("blockstore: disk full"));
+        let report = server.api().mfs().sync(&dir, "/www", &SyncOptions::new());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(report.is_err());
+        assert_eq!(calls(&server), ["files/rm /www/gone", "add "]);
+    }
+
+    #[test]
+    fn test_sync_hash_by_daemon() {
+        let dir = local_dir("daemon");
+
+        // The importer only supports fixed-size chunks.
+        let server = server();
+        let add = AddOptions::new().chunker("rabin");
+        let opts = SyncOptions::new().add_options(add).dry_run(true);
+        let report = server.api().mfs().sync(&dir, "/www", &opts);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.unwrap().unchanged, 0);
+        assert_eq!(calls(&server), ["add "]);
+        let requests = server.requests();
+        let add = requests.iter().find(|r| r.endpoint == "add").unwrap();
+        assert_eq!(add.query_value("only-hash"), Some("true"));
+        assert_eq!(add.query_value("chunker"), Some("rabin"));
+    }
+
+    #[test]
+    fn test_sync_missing_directory() {
+        let dir = local_dir("missing");
+
+        let server = MockServer::start();
+        server.on("files/stat", MockResponse::error(NOT_FOUND_MESSAGE));
+        server.on(
+            "add",
+            MockResponse::json(&json!({"Name": "", "Hash": NEW, "Size": "3"})),
+        );
+        for endpoint in ["files/mkdir", "files/cp"] {
+            server.on(endpoint, MockResponse::new(200, ""));
+        }
+
+        let report = server.api().mfs().sync(&dir, "/www", &SyncOptions::new());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let report = report.unwrap();
+        assert_eq!(
+            report.added,
+            ["changed.txt", "same.txt", "sub", "sub/new.txt"]
+        );
+        let request = &server.requests()[1];
+        assert_eq!(request.endpoint, "files/mkdir");
+        assert_eq!(request.args(), ["/www"]);
+        assert_eq!(request.query_value("parents"), Some("true"));
+    }
+
+    #[test]
+    fn test_sync_not_a_directory() {
+        let dir = local_dir("file");
+
+        let server = MockServer::start();
+        server.on(
+            "files/stat",
+            MockResponse::json(&json!({
+                "Hash": NEW,
+                "Size": 3,
+                "CumulativeSize": 11,
+                "Blocks": 0,
+                "Type": "file",
+            })),
+        );
+
+        let report = server.api().mfs().sync(&dir, "/www", &SyncOptions::new());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        match report {
+            Err(Error::InvalidInput(msg)) => {
+                assert_eq!(msg, "/www is not a directory")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(calls(&server).is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{Error, Result};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+
+/// The location of an API server, parsed from a multiaddr.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ApiAddr {
+    /// A TCP address, as a base URL that includes the API path prefix.
+    Http(String),
+
+    /// The path of a Unix domain socket.
+    Unix(PathBuf),
+}
+
+fn invalid(addr: &str, reason: &str) -> Error {
+    Error::InvalidMultiaddr(format!("{}: {}", addr, reason))
+}
+
+/// Parses the multiaddrs that Kubo writes to `$IPFS_PATH/api`, such as
+/// `/ip4/127.0.0.1/tcp/5001` or `/unix/run/ipfs.sock`.
+pub(crate) fn parse(addr: &str) -> Result<ApiAddr> {
+    let addr = addr.trim();
+
+    let mut parts = match addr.strip_prefix('/') {
+        Some(rest) => rest.split('/'),
+        None => return Err(invalid(addr, "must start with '/'")),
+    };
+
+    let mut host = None;
+    let mut port = None;
+    let mut scheme = "http";
+
+    while let Some(proto) = parts.next() {
+        match proto {
+            "ip4" => {
+                let value = parts.next().unwrap_or_default();
+                let ip: Ipv4Addr = value
+                    .parse()
+                    .map_err(|_| invalid(addr, "invalid ip4 address"))?;
+                host = Some(ip.to_string());
+            }
+            "ip6" => {
+                let value = parts.next().unwrap_or_default();
+                let ip: Ipv6Addr = value
+                    .parse()
+                    .map_err(|_| invalid(addr, "invalid ip6 address"))?;
+                host = Some(format!("[{}]", ip));
+            }
+            "dns" | "dns4" | "dns6" => match parts.next() {
+                Some(name) if !name.is_empty() => host = Some(name.into()),
+                _ => return Err(invalid(addr, "missing dns name")),
+            },
+            "tcp" => {
+                let value = parts.next().unwrap_or_default();
+                let p: u16 = value
+                    .parse()
+                    .map_err(|_| invalid(addr, "invalid tcp port"))?;
+                port = Some(p);
+            }
+            "http" => {}
+            "https" | "tls" => scheme = "https",
+            "unix" => {
+                let path = parts.collect::<Vec<_>>().join("/");
+                if path.is_empty() {
+                    return Err(invalid(addr, "missing unix socket path"));
+                }
+                return Ok(ApiAddr::Unix(format!("/{}", path).into()));
+            }
+            _ => {
+                return Err(invalid(addr, "unsupported protocol"));
+            }
+        }
+    }
+
+    match (host, port) {
+        (Some(host), Some(port)) => Ok(ApiAddr::Http(format!(
+            "{}://{}:{}/api/v0",
+            scheme, host, port
+        ))),
+        (None, _) => Err(invalid(addr, "missing ip4, ip6 or dns address")),
+        (_, None) => Err(invalid(addr, "missing tcp port")),
+    }
+}
+
+/// Returns the IPFS repository path, which is `$IPFS_PATH` or `~/.ipfs`.
+fn repo_path() -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os("IPFS_PATH") {
+        return Ok(path.into());
+    }
+
+    match std::env::var_os("HOME") {
+        Some(home) => Ok(PathBuf::from(home).join(".ipfs")),
+        None => Err(Error::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "neither IPFS_PATH nor HOME is set",
+        ))),
+    }
+}
+
+/// Reads the API multiaddr the daemon advertises in `$IPFS_PATH/api`.
+pub(crate) fn read_api_file() -> Result<String> {
+    let path = repo_path()?.join("api");
+    Ok(std::fs::read_to_string(path)?.trim().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, ApiAddr};
+
+    fn http(url: &str) -> ApiAddr {
+        ApiAddr::Http(url.into())
+    }
+
+    #[test]
+    fn test_parse_tcp() {
+        assert_eq!(
+            parse("/ip4/127.0.0.1/tcp/5001").unwrap(),
+            http("http://127.0.0.1:5001/api/v0")
+        );
+        assert_eq!(
+            parse("/ip6/::1/tcp/5001\n").unwrap(),
+            http("http://[::1]:5001/api/v0")
+        );
+        assert_eq!(
+            parse("/dns4/ipfs.example.com/tcp/443/https").unwrap(),
+            http("https://ipfs.example.com:443/api/v0")
+        );
+        assert_eq!(
+            parse("/dns/node/tcp/5001/http").unwrap(),
+            http("http://node:5001/api/v0")
+        );
+    }
+
+    #[test]
+    fn test_parse_unix() {
+        assert_eq!(
+            parse("/unix/run/ipfs.sock").unwrap(),
+            ApiAddr::Unix("/run/ipfs.sock".into())
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse("127.0.0.1:5001").is_err());
+        assert!(parse("/ip4/127.0.0.1").is_err());
+        assert!(parse("/ip4/localhost/tcp/5001").is_err());
+        assert!(parse("/tcp/5001").is_err());
+        assert!(parse("/ip4/127.0.0.1/udp/5001").is_err());
+        assert!(parse("/unix").is_err());
+    }
+}
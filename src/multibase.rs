@@ -0,0 +1,414 @@
+//! Multibase, the self-describing string encodings of binary data used by
+//! CIDs and IPNS names.
+//!
+//! A multibase string starts with a character naming its base, so the same
+//! bytes can be written as `bafy...` in base32 or `zdj7...` in base58btc.
+//! Everything here works offline.
+//!
+//! ```rust
+//! use ipfsapi::multibase::{self, Base};
+//!
+//! let encoded = multibase::encode(Base::Base36, b"Hello world");
+//! assert_eq!(encoded, "kazw5bz2xp6xr4cpk4");
+//!
+//! let (base, bytes) = multibase::decode(&encoded)?;
+//! assert_eq!(base, Base::Base36);
+//! assert_eq!(bytes, b"Hello world");
+//! # Ok::<(), ipfsapi::Error>(())
+//! ```
+
+use crate::{Error, Result};
+use std::fmt::Display;
+use std::str::FromStr;
+
+const BASE16_ALPHABET: &[u8] = b"0123456789abcdef";
+const BASE32_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+const BASE36_ALPHABET: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+const BASE58_ALPHABET: &[u8] =
+    b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BASE64_ALPHABET: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64URL_ALPHABET: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A multibase encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Base {
+    /// Lowercase hexadecimal, prefix `f`.
+    Base16,
+
+    /// Uppercase hexadecimal, prefix `F`.
+    Base16Upper,
+
+    /// Lowercase RFC 4648 base32 without padding, prefix `b`. This is the
+    /// default for CIDv1.
+    Base32,
+
+    /// Uppercase RFC 4648 base32 without padding, prefix `B`.
+    Base32Upper,
+
+    /// Lowercase base36, prefix `k`. This is the default for IPNS names.
+    Base36,
+
+    /// Uppercase base36, prefix `K`.
+    Base36Upper,
+
+    /// The Bitcoin base58 alphabet, prefix `z`. CIDv0 are written in it
+    /// without the prefix.
+    Base58Btc,
+
+    /// RFC 4648 base64 without padding, prefix `m`.
+    Base64,
+
+    /// RFC 4648 base64 with padding, prefix `M`.
+    Base64Pad,
+
+    /// RFC 4648 URL-safe base64 without padding, prefix `u`.
+    Base64Url,
+
+    /// RFC 4648 URL-safe base64 with padding, prefix `U`.
+    Base64UrlPad,
+}
+
+const BASES: [Base; 11] = [
+    Base::Base16,
+    Base::Base16Upper,
+    Base::Base32,
+    Base::Base32Upper,
+    Base::Base36,
+    Base::Base36Upper,
+    Base::Base58Btc,
+    Base::Base64,
+    Base::Base64Pad,
+    Base::Base64Url,
+    Base::Base64UrlPad,
+];
+
+impl Base {
+    /// Returns the prefix character of the base.
+    pub fn code(self) -> char {
+        match self {
+            Base::Base16 => 'f',
+            Base::Base16Upper => 'F',
+            Base::Base32 => 'b',
+            Base::Base32Upper => 'B',
+            Base::Base36 => 'k',
+            Base::Base36Upper => 'K',
+            Base::Base58Btc => 'z',
+            Base::Base64 => 'm',
+            Base::Base64Pad => 'M',
+            Base::Base64Url => 'u',
+            Base::Base64UrlPad => 'U',
+        }
+    }
+
+    /// Returns the base with the given prefix character.
+    pub fn from_code(code: char) -> Option<Self> {
+        BASES.into_iter().find(|base| base.code() == code)
+    }
+
+    /// Returns the name of the base, as used by the `-b` option of
+    /// `ipfs cid format`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Base::Base16 => "base16",
+            Base::Base16Upper => "base16upper",
+            Base::Base32 => "base32",
+            Base::Base32Upper => "base32upper",
+            Base::Base36 => "base36",
+            Base::Base36Upper => "base36upper",
+            Base::Base58Btc => "base58btc",
+            Base::Base64 => "base64",
+            Base::Base64Pad => "base64pad",
+            Base::Base64Url => "base64url",
+            Base::Base64UrlPad => "base64urlpad",
+        }
+    }
+
+    /// Encodes bytes in this base, without the prefix.
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Base::Base16 => encode_bits(bytes, BASE16_ALPHABET, false),
+            Base::Base16Upper => {
+                encode_bits(bytes, BASE16_ALPHABET, false).to_ascii_uppercase()
+            }
+            Base::Base32 => encode_bits(bytes, BASE32_ALPHABET, false),
+            Base::Base32Upper => {
+                encode_bits(bytes, BASE32_ALPHABET, false).to_ascii_uppercase()
+            }
+            Base::Base36 => encode_radix(bytes, BASE36_ALPHABET),
+            Base::Base36Upper => {
+                encode_radix(bytes, BASE36_ALPHABET).to_ascii_uppercase()
+            }
+            Base::Base58Btc => encode_radix(bytes, BASE58_ALPHABET),
+            Base::Base64 => encode_bits(bytes, BASE64_ALPHABET, false),
+            Base::Base64Pad => encode_bits(bytes, BASE64_ALPHABET, true),
+            Base::Base64Url => encode_bits(bytes, BASE64URL_ALPHABET, false),
+            Base::Base64UrlPad => encode_bits(bytes, BASE64URL_ALPHABET, true),
+        }
+    }
+
+    /// Decodes a string in this base, without the prefix.
+    ///
+    /// The base16, base32 and base36 bases accept either case, like Kubo.
+    pub fn decode(self, s: &str) -> Result<Vec<u8>> {
+        let decoded = match self {
+            Base::Base16 | Base::Base16Upper => {
+                decode_bits(&s.to_ascii_lowercase(), BASE16_ALPHABET, false)
+            }
+            Base::Base32 | Base::Base32Upper => {
+                decode_bits(&s.to_ascii_lowercase(), BASE32_ALPHABET, false)
+            }
+            Base::Base36 | Base::Base36Upper => {
+                decode_radix(&s.to_ascii_lowercase(), BASE36_ALPHABET)
+            }
+            Base::Base58Btc => decode_radix(s, BASE58_ALPHABET),
+            Base::Base64 => decode_bits(s, BASE64_ALPHABET, false),
+            Base::Base64Pad => decode_bits(s, BASE64_ALPHABET, true),
+            Base::Base64Url => decode_bits(s, BASE64URL_ALPHABET, false),
+            Base::Base64UrlPad => decode_bits(s, BASE64URL_ALPHABET, true),
+        };
+
+        decoded.ok_or_else(|| {
+            let msg = format!("{:?} is not valid {}", s, self.name());
+            Error::InvalidMultibase(msg)
+        })
+    }
+}
+
+impl Display for Base {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Base {
+    type Err = Error;
+
+    /// Parses the name of a base, such as `base32`.
+    fn from_str(s: &str) -> Result<Self> {
+        BASES
+            .into_iter()
+            .find(|base| base.name() == s)
+            .ok_or_else(|| {
+                Error::InvalidMultibase(format!("unknown base {:?}", s))
+            })
+    }
+}
+
+/// Encodes bytes as a multibase string, starting with the prefix of `base`.
+pub fn encode(base: Base, bytes: &[u8]) -> String {
+    let mut out = String::from(base.code());
+    out.push_str(&base.encode(bytes));
+    out
+}
+
+/// Decodes a multibase string, and returns its base and bytes.
+pub fn decode(s: &str) -> Result<(Base, Vec<u8>)> {
+    let mut chars = s.chars();
+    let base = chars.next().and_then(Base::from_code).ok_or_else(|| {
+        let msg = format!("{:?} has an unknown multibase prefix", s);
+        Error::InvalidMultibase(msg)
+    })?;
+
+    Ok((base, base.decode(chars.as_str())?))
+}
+
+/// Encodes bytes with an alphabet of 16, 32 or 64 characters, where every
+/// character holds the same number of bits.
+fn encode_bits(bytes: &[u8], alphabet: &[u8], pad: bool) -> String {
+    let width = alphabet.len().trailing_zeros();
+    let mask = (1 << width) - 1;
+
+    let mut out =
+        String::with_capacity((bytes.len() * 8).div_ceil(width as usize));
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = buffer << 8 | u32::from(byte);
+        bits += 8;
+        while bits >= width {
+            bits -= width;
+            out.push(alphabet[(buffer >> bits) as usize & mask] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(alphabet[(buffer << (width - bits)) as usize & mask] as char);
+    }
+
+    if pad {
+        while !out.len().is_multiple_of(block_len(width)) {
+            out.push('=');
+        }
+    }
+
+    out
+}
+
+/// Decodes a string made by [`encode_bits`]. Returns `None` if it has
+/// invalid characters, trailing bits or wrong padding.
+fn decode_bits(s: &str, alphabet: &[u8], pad: bool) -> Option<Vec<u8>> {
+    let width = alphabet.len().trailing_zeros();
+
+    let digits = if pad { s.trim_end_matches('=') } else { s };
+
+    let mut out = Vec::with_capacity(digits.len() * width as usize / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in digits.bytes() {
+        let value = alphabet.iter().position(|&a| a == c)? as u32;
+        buffer = (buffer << width | value) & 0xffff;
+        bits += width;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+
+    // A character without a whole byte in it, or unused bits that are not
+    // zero, would make the encoding ambiguous.
+    if bits >= width || buffer & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+
+    // The padding must be the shortest that fills the last block.
+    if pad && encode_bits(&out, alphabet, true).len() != s.len() {
+        return None;
+    }
+
+    Some(out)
+}
+
+/// Returns the number of characters that hold a whole number of bytes.
+fn block_len(width: u32) -> usize {
+    match width {
+        5 => 8,
+        6 => 4,
+        _ => 1,
+    }
+}
+
+/// Encodes bytes as a big-endian number in the base of the alphabet, with
+/// leading zero bytes written as leading zero digits.
+fn encode_radix(bytes: &[u8], alphabet: &[u8]) -> String {
+    let radix = alphabet.len() as u32;
+
+    // The digits of the number, least significant first.
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 2);
+    for &byte in bytes {
+        let mut carry = u32::from(byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % radix) as u8;
+            carry /= radix;
+        }
+        while carry > 0 {
+            digits.push((carry % radix) as u8);
+            carry /= radix;
+        }
+    }
+
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+    let leading = std::iter::repeat_n(alphabet[0] as char, zeros);
+    let rest = digits.iter().rev().map(|&d| alphabet[d as usize] as char);
+    leading.chain(rest).collect()
+}
+
+/// Decodes a string made by [`encode_radix`]. Returns `None` if it has
+/// invalid characters.
+fn decode_radix(s: &str, alphabet: &[u8]) -> Option<Vec<u8>> {
+    let radix = alphabet.len() as u32;
+
+    // The bytes of the number, least significant first.
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len());
+    for c in s.bytes() {
+        let mut carry = alphabet.iter().position(|&a| a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * radix;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let zeros = s.bytes().take_while(|&c| c == alphabet[0]).count();
+    bytes.extend(std::iter::repeat_n(0, zeros));
+    bytes.reverse();
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, Base};
+
+    #[test]
+    fn test_bases() {
+        // The vectors of the multibase specification.
+        let bytes = b"yes mani !";
+        for (base, encoded) in [
+            (Base::Base16, "f796573206d616e692021"),
+            (Base::Base16Upper, "F796573206D616E692021"),
+            (Base::Base32, "bpfsxgidnmfxgsibb"),
+            (Base::Base32Upper, "BPFSXGIDNMFXGSIBB"),
+            (Base::Base36, "k2lcpzo5yikidynfl"),
+            (Base::Base36Upper, "K2LCPZO5YIKIDYNFL"),
+            (Base::Base58Btc, "z7paNL19xttacUY"),
+            (Base::Base64, "meWVzIG1hbmkgIQ"),
+            (Base::Base64Pad, "MeWVzIG1hbmkgIQ=="),
+            (Base::Base64Url, "ueWVzIG1hbmkgIQ"),
+            (Base::Base64UrlPad, "UeWVzIG1hbmkgIQ=="),
+        ] {
+            assert_eq!(encode(base, bytes), encoded);
+            assert_eq!(decode(encoded).unwrap(), (base, bytes.to_vec()));
+            assert_eq!(base.name().parse::<Base>().unwrap(), base);
+        }
+
+        // Leading zero bytes.
+        let bytes = b"\0\0yes mani !";
+        for (base, encoded) in [
+            (Base::Base16, "f0000796573206d616e692021"),
+            (Base::Base36, "k002lcpzo5yikidynfl"),
+            (Base::Base58Btc, "z117paNL19xttacUY"),
+        ] {
+            assert_eq!(encode(base, bytes), encoded);
+            assert_eq!(decode(encoded).unwrap(), (base, bytes.to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_rfc4648() {
+        for (bytes, base32, base64) in [
+            (&b""[..], "", ""),
+            (b"f", "my", "Zg=="),
+            (b"fo", "mzxq", "Zm8="),
+            (b"foo", "mzxw6", "Zm9v"),
+            (b"foob", "mzxw6yq", "Zm9vYg=="),
+            (b"fooba", "mzxw6ytb", "Zm9vYmE="),
+            (b"foobar", "mzxw6ytboi", "Zm9vYmFy"),
+        ] {
+            assert_eq!(Base::Base32.encode(bytes), base32);
+            assert_eq!(Base::Base32.decode(base32).unwrap(), bytes);
+            let upper = base32.to_uppercase();
+            assert_eq!(Base::Base32.decode(&upper).unwrap(), bytes);
+            assert_eq!(Base::Base64Pad.encode(bytes), base64);
+            assert_eq!(Base::Base64Pad.decode(base64).unwrap(), bytes);
+            let unpadded = base64.trim_end_matches('=');
+            assert_eq!(Base::Base64.decode(unpadded).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        for invalid in [
+            "", "x1234", "fabc", "f0g", "bm", "bmz", "z0OIl", "mZg=", "MZg=",
+            "MZg", "MZm8==", "u+/",
+        ] {
+            assert!(decode(invalid).is_err(), "{} was decoded", invalid);
+        }
+        assert!("base99".parse::<Base>().is_err());
+    }
+}
@@ -0,0 +1,426 @@
+//! Streaming `multipart/form-data` bodies for upload endpoints.
+//!
+//! The parts of a [`Multipart`] body are read from any [`Read`] source while
+//! the request is being sent, so large files do not have to be loaded into
+//! memory first.
+//!
+//! The boundary never occurs inside the body. It is regenerated until it is
+//! absent from the parts that are held in memory, and streamed parts are
+//! scanned while they are read. A streamed part that turns out to contain the
+//! boundary fails with an [`InvalidData`](std::io::ErrorKind::InvalidData)
+//! error instead of producing a corrupt body.
+//!
+//! ```rust
+//! use ipfsapi::multipart::{Multipart, Part};
+//! use std::io::Read;
+//!
+//! let mut body = Multipart::new();
+//! body.add_part(Part::new("file", &b"Hello world"[..]).filename("hello.txt"));
+//!
+//! let content_type = body.content_type();
+//! let mut encoded = Vec::new();
+//! body.into_reader().read_to_end(&mut encoded)?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::io::{Cursor, Read};
+
+/// The contents of a [`Part`].
+enum Body<'a> {
+    /// Contents held in memory, which can be checked for the boundary before
+    /// the body is sent.
+    Bytes(Cursor<Cow<'a, [u8]>>),
+
+    /// Contents streamed from a reader.
+    Reader(Box<dyn Read + Send + 'a>),
+}
+
+/// A single part of a [`Multipart`] body.
+pub struct Part<'a> {
+    name: String,
+    filename: Option<String>,
+    headers: Vec<(String, String)>,
+    body: Body<'a>,
+}
+
+impl<'a> Part<'a> {
+    /// Creates a part with the given form field name, that streams its
+    /// contents from `body`.
+    pub fn new(name: &str, body: impl Read + Send + 'a) -> Self {
+        Self {
+            name: name.into(),
+            filename: None,
+            headers: Vec::new(),
+            body: Body::Reader(Box::new(body)),
+        }
+    }
+
+    /// Creates a part with the given form field name, whose contents are
+    /// already in memory.
+    pub fn bytes(name: &str, body: impl Into<Cow<'a, [u8]>>) -> Self {
+        Self {
+            name: name.into(),
+            filename: None,
+            headers: Vec::new(),
+            body: Body::Bytes(Cursor::new(body.into())),
+        }
+    }
+
+    /// Sets the filename of the part.
+    #[must_use]
+    pub fn filename(mut self, filename: &str) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Adds a header to the part, such as `Content-Type`.
+    #[must_use]
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Encodes the boundary line and the headers that precede the body.
+    fn head(&self, boundary: &str) -> Vec<u8> {
+        let mut head = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            boundary,
+            quote(&self.name)
+        );
+
+        if let Some(filename) = &self.filename {
+            head.push_str(&format!("; filename=\"{}\"", quote(filename)));
+        }
+        head.push_str("\r\n");
+
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+
+        head.into_bytes()
+    }
+
+    /// Returns whether the boundary occurs in the headers or in the contents
+    /// held in memory.
+    fn contains(&self, boundary: &str) -> bool {
+        let delimiter = format!("--{}", boundary);
+        let head = self.head(boundary);
+
+        find(&head[delimiter.len()..], delimiter.as_bytes()).is_some()
+            || match &self.body {
+                Body::Bytes(bytes) => {
+                    find(bytes.get_ref(), delimiter.as_bytes()).is_some()
+                }
+                Body::Reader(_) => false,
+            }
+    }
+}
+
+impl Debug for Part<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Part")
+            .field("name", &self.name)
+            .field("filename", &self.filename)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Escapes a header parameter value for use inside double quotes.
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// A `multipart/form-data` body made of streamed parts.
+#[derive(Debug)]
+pub struct Multipart<'a> {
+    boundary: String,
+    parts: Vec<Part<'a>>,
+}
+
+impl Default for Multipart<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Multipart<'a> {
+    /// Creates an empty body with a random boundary.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            boundary: new_boundary(),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Appends a part to the body.
+    pub fn add_part(&mut self, part: Part<'a>) {
+        self.parts.push(part);
+
+        while self.parts.iter().any(|part| part.contains(&self.boundary)) {
+            self.boundary = new_boundary();
+        }
+    }
+
+    /// Returns the value of the `Content-Type` header for this body.
+    ///
+    /// The boundary changes if a part that contains it is added, so this
+    /// should be called after the last part has been added.
+    #[must_use]
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary=\"{}\"", self.boundary)
+    }
+
+    /// Returns a reader that produces the encoded body.
+    #[must_use]
+    pub fn into_reader(self) -> MultipartReader<'a> {
+        MultipartReader {
+            scanner: Scanner::new(&self.boundary),
+            boundary: self.boundary,
+            parts: self.parts.into_iter(),
+            body: None,
+            pending: Cursor::new(Vec::new()),
+            finished: false,
+        }
+    }
+}
+
+/// Encodes a body with a single part whose contents are streamed from an
+/// async reader, and returns its `Content-Type` and the body.
+#[cfg(feature = "async")]
+pub(crate) fn async_body(
+    name: &str,
+    filename: Option<&str>,
+    headers: &[(&str, &str)],
+    reader: impl tokio::io::AsyncRead + Send + 'static,
+) -> (String, reqwest::Body) {
+    use bytes::Bytes;
+    use futures_util::{stream, StreamExt};
+
+    // The part only holds the headers, its contents are streamed below.
+    let mut part = Part::bytes(name, &[][..]);
+    if let Some(filename) = filename {
+        part = part.filename(filename);
+    }
+    for (name, value) in headers {
+        part = part.header(name, value);
+    }
+
+    let mut body = Multipart::new();
+    body.add_part(part);
+    let content_type = body.content_type();
+
+    let head = Bytes::from(body.parts[0].head(&body.boundary));
+    let end = Bytes::from(format!("\r\n--{}--\r\n", body.boundary));
+    let mut scanner = Scanner::new(&body.boundary);
+    let contents =
+        tokio_util::io::ReaderStream::new(reader).map(move |chunk| {
+            let chunk = chunk?;
+            scanner.scan(&chunk)?;
+            Ok(chunk)
+        });
+
+    let encoded = stream::once(async { Ok::<_, std::io::Error>(head) })
+        .chain(contents)
+        .chain(stream::once(async { Ok(end) }));
+    (content_type, reqwest::Body::wrap_stream(encoded))
+}
+
+/// Generates a random boundary of 32 hex digits.
+fn new_boundary() -> String {
+    crate::rand::feed_event("multipart");
+
+    let mut boundary = String::with_capacity(32);
+    for _ in 0..32 {
+        boundary.push(crate::rand::hex_digit());
+    }
+
+    boundary
+}
+
+/// Checks streamed contents for the boundary, including occurrences that
+/// span several reads.
+struct Scanner {
+    delimiter: Vec<u8>,
+    tail: Vec<u8>,
+}
+
+impl Scanner {
+    fn new(boundary: &str) -> Self {
+        Self {
+            delimiter: format!("--{}", boundary).into_bytes(),
+            tail: Vec::new(),
+        }
+    }
+
+    /// Forgets the contents scanned so far, at the start of a new part.
+    fn reset(&mut self) {
+        self.tail.clear();
+    }
+
+    fn scan(&mut self, data: &[u8]) -> std::io::Result<()> {
+        self.tail.extend_from_slice(data);
+        if find(&self.tail, &self.delimiter).is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "the multipart boundary occurs in a streamed part",
+            ));
+        }
+
+        let keep = self.delimiter.len() - 1;
+        if self.tail.len() > keep {
+            self.tail.drain(..self.tail.len() - keep);
+        }
+
+        Ok(())
+    }
+}
+
+/// Produces the encoded bytes of a [`Multipart`] body.
+pub struct MultipartReader<'a> {
+    boundary: String,
+    scanner: Scanner,
+    parts: std::vec::IntoIter<Part<'a>>,
+    body: Option<Body<'a>>,
+    pending: Cursor<Vec<u8>>,
+    finished: bool,
+}
+
+impl Read for MultipartReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let n = self.pending.read(buf)?;
+            if n > 0 {
+                return Ok(n);
+            }
+
+            if let Some(body) = &mut self.body {
+                let n = match body {
+                    Body::Bytes(bytes) => bytes.read(buf)?,
+                    Body::Reader(reader) => {
+                        let n = reader.read(buf)?;
+                        self.scanner.scan(&buf[..n])?;
+                        n
+                    }
+                };
+                if n > 0 {
+                    return Ok(n);
+                }
+
+                self.body = None;
+                self.pending = Cursor::new(b"\r\n".to_vec());
+                continue;
+            }
+
+            if let Some(part) = self.parts.next() {
+                self.pending = Cursor::new(part.head(&self.boundary));
+                self.body = Some(part.body);
+                self.scanner.reset();
+                continue;
+            }
+
+            if self.finished {
+                return Ok(0);
+            }
+
+            self.finished = true;
+            let end = format!("--{}--\r\n", self.boundary);
+            self.pending = Cursor::new(end.into_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Multipart, Part};
+    use crate::testing::{MockResponse, MockServer};
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn test_encode_parts() {
+        let mut body = Multipart::new();
+        body.boundary = "b0undary".into();
+
+        body.add_part(Part::new("data", &b"Hello world"[..]));
+        body.add_part(
+            Part::new("file", &b"{}"[..])
+                .filename("dir/\"quoted\".json")
+                .header("Content-Type", "application/json"),
+        );
+
+        assert_eq!(
+            body.content_type(),
+            "multipart/form-data; boundary=\"b0undary\""
+        );
+
+        let mut encoded = String::new();
+        body.into_reader().read_to_string(&mut encoded).unwrap();
+
+        assert_eq!(
+            encoded,
+            "--b0undary\r\n\
+             Content-Disposition: form-data; name=\"data\"\r\n\
+             \r\n\
+             Hello world\r\n\
+             --b0undary\r\n\
+             Content-Disposition: form-data; name=\"file\"; \
+             filename=\"dir/\\\"quoted\\\".json\"\r\n\
+             Content-Type: application/json\r\n\
+             \r\n\
+             {}\r\n\
+             --b0undary--\r\n"
+        );
+    }
+
+    #[test]
+    fn test_boundary_collision() {
+        let mut body = Multipart::new();
+        let first = body.boundary.clone();
+
+        let payload = format!("\r\n--{}\r\n--{}--\r\n", first, first);
+        body.add_part(Part::bytes("data", payload.clone().into_bytes()));
+        assert_ne!(body.boundary, first);
+
+        let server = MockServer::start();
+        server.on("block/put", MockResponse::new(200, "{}"));
+        server
+            .api()
+            .post("block/put")
+            .set("Content-Type", &body.content_type())
+            .send(body.into_reader())
+            .unwrap();
+
+        let parts = server.requests()[0].parts().unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].body, payload.as_bytes());
+    }
+
+    #[test]
+    fn test_streamed_boundary_collision() {
+        let mut body = Multipart::new();
+        let delimiter = format!("--{}", body.boundary);
+
+        // Split the boundary across two reads.
+        let mut a = delimiter.into_bytes();
+        let b = a.split_off(10);
+        let data = Cursor::new(a).chain(Cursor::new(b));
+        body.add_part(Part::new("data", data));
+
+        let err = body.into_reader().read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -1,14 +1,18 @@
-use crate::IpfsApi;
-use std::error::Error;
+use crate::cid::Cid;
+use crate::{Error, IpfsApi, Result};
 
 impl IpfsApi {
-    /// Publish an IPFS hash in IPNS.
-    pub fn pin_add(&self, hash: &str) -> Result<Vec<String>, Box<dyn Error>> {
-        let endpoint = "api/v0/pin/add";
-        let url = format!("http://{}:{}/{}", self.server, self.port, endpoint);
-
-        let resp = ureq::post(&url)
-            .query("arg", hash)
+    /// Pins a CID recursively. Returns the pinned CIDs.
+    ///
+    /// The CID can be a [`Cid`] or its string form.
+    pub fn pin_add(
+        &self,
+        cid: impl TryInto<Cid, Error = impl Into<Error>>,
+    ) -> Result<Vec<Cid>> {
+        let cid: Cid = cid.try_into().map_err(Into::into)?;
+        let resp = self
+            .post("pin/add")
+            .query("arg", &cid.to_string())
             .query("recursive", "true")
             .query("progress", "false")
             .call()?;
@@ -16,13 +20,95 @@ impl IpfsApi {
         let resp = resp.into_reader();
         let resp: serde_json::Value = serde_json::from_reader(resp)?;
 
-        let mut pins = Vec::<String>::new();
+        parse_pins(&resp)
+    }
+
+    /// Pins a CID recursively like [`pin_add`](Self::pin_add), and
+    /// calls `on_progress` with the number of blocks fetched so far while
+    /// the daemon fetches the DAG.
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let hash = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u";
+    /// api.pin_add_with_progress(hash, |blocks| {
+    ///     eprint!("\r{} blocks fetched", blocks);
+    /// })?;
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    pub fn pin_add_with_progress(
+        &self,
+        cid: impl TryInto<Cid, Error = impl Into<Error>>,
+        mut on_progress: impl FnMut(u64),
+    ) -> Result<Vec<Cid>> {
+        let cid: Cid = cid.try_into().map_err(Into::into)?;
+        let resp = self
+            .post("pin/add")
+            .query("arg", &cid.to_string())
+            .query("recursive", "true")
+            .query("progress", "true")
+            .call()?;
+
+        for line in resp.into_json_lines() {
+            let line = line?;
 
-        for val in resp["Pins"].as_array().ok_or("Pins is not an array")? {
-            let str_val = val.as_str().ok_or("Value is not a string")?;
-            pins.push(str_val.to_string());
+            if line.get("Pins").is_some() {
+                return parse_pins(&line);
+            }
+            if let Some(blocks) = line["Progress"].as_u64() {
+                on_progress(blocks);
+            }
         }
 
-        Ok(pins)
+        Err(Error::response("the response did not include Pins"))
+    }
+}
+
+pub(crate) fn parse_pins(resp: &serde_json::Value) -> Result<Vec<Cid>> {
+    let mut pins = Vec::new();
+
+    let vals = resp["Pins"]
+        .as_array()
+        .ok_or_else(|| Error::response("Pins is not an array"))?;
+
+    for val in vals {
+        let str_val = val
+            .as_str()
+            .ok_or_else(|| Error::response("Value is not a string"))?;
+        pins.push(str_val.parse()?);
+    }
+
+    Ok(pins)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{MockResponse, MockServer};
+    use serde_json::json;
+
+    const HASH: &str = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u";
+
+    #[test]
+    fn test_pin_add_with_progress() {
+        let server = MockServer::start();
+        server.on(
+            "pin/add",
+            MockResponse::ndjson([
+                json!({"Progress": 1}),
+                json!({"Progress": 12}),
+                json!({"Pins": [HASH], "Progress": 12}),
+            ]),
+        );
+
+        let mut progress = Vec::new();
+        let pins = server
+            .api()
+            .pin_add_with_progress(HASH, |blocks| progress.push(blocks))
+            .unwrap();
+
+        assert_eq!(pins[0].to_string(), HASH);
+        assert_eq!(progress, [1, 12]);
+        assert_eq!(server.requests()[0].query_value("progress"), Some("true"));
     }
 }
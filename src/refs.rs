@@ -0,0 +1,251 @@
+//! Types for listing the blocks referenced by a DAG with [`IpfsApi::refs`],
+//! and the blocks of the local repository with [`IpfsApi::refs_local`].
+
+use crate::stream::JsonLines;
+use crate::{Error, IpfsApi, Result};
+use serde_json::Value;
+use std::fmt::Debug;
+use std::io::Read;
+
+/// Options of the `/api/v0/refs` endpoint.
+///
+/// ```rust
+/// use ipfsapi::refs::RefsOptions;
+///
+/// let opts = RefsOptions::new().recursive(true).unique(true).max_depth(2);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RefsOptions {
+    recursive: Option<bool>,
+    unique: Option<bool>,
+    max_depth: Option<i64>,
+    edges: Option<bool>,
+    format: Option<String>,
+}
+
+impl RefsOptions {
+    /// Creates options that list the direct links of the root.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lists the links of the whole DAG instead of the direct links.
+    #[must_use]
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = Some(recursive);
+        self
+    }
+
+    /// Lists each block only once.
+    #[must_use]
+    pub fn unique(mut self, unique: bool) -> Self {
+        self.unique = Some(unique);
+        self
+    }
+
+    /// Sets the depth of the recursive listing, where `-1` is unlimited.
+    /// This enables the recursive listing.
+    #[must_use]
+    pub fn max_depth(mut self, depth: i64) -> Self {
+        self.recursive = Some(true);
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Lists the links as `<src> -> <dst>` edges.
+    #[must_use]
+    pub fn edges(mut self, edges: bool) -> Self {
+        self.edges = Some(edges);
+        self
+    }
+
+    /// Sets the format of the references, using the `<src>`, `<dst>` and
+    /// `<linkname>` placeholders.
+    #[must_use]
+    pub fn format(mut self, format: &str) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+}
+
+/// A reference listed by [`IpfsApi::refs`] or [`IpfsApi::refs_local`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ref {
+    /// The reference, a CID unless the options changed its format.
+    pub r#ref: String,
+
+    /// The error that happened while listing this reference, such as a
+    /// block that could not be fetched.
+    pub err: Option<String>,
+}
+
+impl Ref {
+    fn from_json(val: &Value) -> Result<Self> {
+        let r#ref = val["Ref"]
+            .as_str()
+            .ok_or_else(|| Error::response("Ref is not a string"))?;
+        let err = val["Err"]
+            .as_str()
+            .filter(|err| !err.is_empty())
+            .map(String::from);
+
+        Ok(Self {
+            r#ref: r#ref.into(),
+            err,
+        })
+    }
+}
+
+/// An iterator over references, returned by [`IpfsApi::refs`] and
+/// [`IpfsApi::refs_local`]. The iterator ends after the first error.
+pub struct RefStream {
+    lines: JsonLines<Box<dyn Read + Send + Sync>>,
+    failed: bool,
+}
+
+impl Iterator for RefStream {
+    type Item = Result<Ref>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let item = self.lines.next()?.and_then(|line| Ref::from_json(&line));
+        self.failed = item.is_err();
+        Some(item)
+    }
+}
+
+impl Debug for RefStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RefStream").finish_non_exhaustive()
+    }
+}
+
+impl IpfsApi {
+    /// Lists the blocks linked from a path, as they are found.
+    ///
+    /// ```rust,no_run
+    /// # use ipfsapi::IpfsApi;
+    /// use ipfsapi::refs::RefsOptions;
+    ///
+    /// let api = IpfsApi::new("127.0.0.1", 5001);
+    ///
+    /// let opts = RefsOptions::new().recursive(true).unique(true);
+    /// let root = "QmS4ustL54uo8FzR9455qaxZwuMiUhyvMcX9Ba8nUH4uVv";
+    /// for r in api.refs(root, &opts)? {
+    ///     let r = r?;
+    ///     match r.err {
+    ///         Some(err) => eprintln!("{}: {}", r.r#ref, err),
+    ///         None => println!("{}", r.r#ref),
+    ///     }
+    /// }
+    /// # Ok::<(), ipfsapi::Error>(())
+    /// ```
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/refs` API.
+    pub fn refs(
+        &self,
+        path: impl Into<String>,
+        opts: &RefsOptions,
+    ) -> Result<RefStream> {
+        let mut req = self.post("refs").query("arg", &path.into());
+        if let Some(recursive) = opts.recursive {
+            req = req.query("recursive", &recursive.to_string());
+        }
+        if let Some(unique) = opts.unique {
+            req = req.query("unique", &unique.to_string());
+        }
+        if let Some(depth) = opts.max_depth {
+            req = req.query("max-depth", &depth.to_string());
+        }
+        if let Some(edges) = opts.edges {
+            req = req.query("edges", &edges.to_string());
+        }
+        if let Some(format) = &opts.format {
+            req = req.query("format", format);
+        }
+
+        Ok(RefStream {
+            lines: req.call()?.into_json_lines(),
+            failed: false,
+        })
+    }
+
+    /// Lists all blocks of the local repository, as they are found.
+    ///
+    /// # Notes
+    /// This is a wrapper for the `/api/v0/refs/local` API.
+    pub fn refs_local(&self) -> Result<RefStream> {
+        Ok(RefStream {
+            lines: self.post("refs/local").call()?.into_json_lines(),
+            failed: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ref, RefsOptions};
+    use crate::testing::{MockResponse, MockServer};
+    use crate::Error;
+    use serde_json::json;
+
+    #[test]
+    fn test_refs() {
+        let server = MockServer::start();
+        server.on(
+            "refs",
+            MockResponse::ndjson([
+                json!({"Ref": "QmA", "Err": ""}),
+                json!({"Ref": "", "Err": "block was not found locally"}),
+            ])
+            .stream_error("context canceled"),
+        );
+
+        let opts = RefsOptions::new().unique(true).max_depth(2).format("<dst>");
+        let mut refs = server.api().refs("QmRoot", &opts).unwrap();
+        assert_eq!(
+            refs.next().unwrap().unwrap(),
+            Ref {
+                r#ref: "QmA".into(),
+                err: None,
+            }
+        );
+        let missing = refs.next().unwrap().unwrap();
+        assert_eq!(missing.err.as_deref(), Some("block was not found locally"));
+        assert!(matches!(refs.next(), Some(Err(Error::Stream(_)))));
+        assert!(refs.next().is_none());
+
+        let request = &server.requests()[0];
+        assert_eq!(request.args(), ["QmRoot"]);
+        assert_eq!(request.query_value("recursive"), Some("true"));
+        assert_eq!(request.query_value("unique"), Some("true"));
+        assert_eq!(request.query_value("max-depth"), Some("2"));
+        assert_eq!(request.query_value("format"), Some("<dst>"));
+        assert_eq!(request.query_value("edges"), None);
+    }
+
+    #[test]
+    fn test_refs_local() {
+        let server = MockServer::start();
+        server.on(
+            "refs/local",
+            MockResponse::ndjson([
+                json!({"Ref": "QmA", "Err": ""}),
+                json!({"Ref": "QmB", "Err": ""}),
+            ]),
+        );
+
+        let refs: Vec<String> = server
+            .api()
+            .refs_local()
+            .unwrap()
+            .map(|r| r.unwrap().r#ref)
+            .collect();
+        assert_eq!(refs, ["QmA", "QmB"]);
+    }
+}
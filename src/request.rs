@@ -0,0 +1,95 @@
+use crate::stream::{ByteStream, JsonLines};
+use crate::transport::{Request, Response};
+use crate::{Error, IpfsApi, Result};
+use std::io::Read;
+
+/// Builds a request to an API endpoint and sends it through the transport
+/// of an [`IpfsApi`].
+///
+/// This mirrors the small part of the `ureq` request builder that the API
+/// methods use.
+pub(crate) struct RequestBuilder<'a> {
+    api: &'a IpfsApi,
+    endpoint: &'a str,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+}
+
+impl<'a> RequestBuilder<'a> {
+    pub(crate) fn new(api: &'a IpfsApi, endpoint: &'a str) -> Self {
+        let mut headers = api.headers.clone();
+
+        if let Some(auth) = &api.auth {
+            headers.push(("Authorization".into(), auth.header_value()));
+        }
+
+        Self {
+            api,
+            endpoint,
+            query: Vec::new(),
+            headers,
+        }
+    }
+
+    /// Adds a query parameter to the request.
+    pub(crate) fn query(mut self, name: &str, value: &str) -> Self {
+        self.query.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets a header on the request.
+    pub(crate) fn set(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sends the request without a body.
+    pub(crate) fn call(self) -> Result<Response> {
+        self.dispatch(None)
+    }
+
+    /// Sends the request with the given body.
+    pub(crate) fn send(self, mut body: impl Read) -> Result<Response> {
+        self.dispatch(Some(&mut body))
+    }
+
+    /// Sends the request, and turns unsuccessful responses into errors.
+    fn dispatch(self, body: Option<&mut dyn Read>) -> Result<Response> {
+        let request = Request {
+            endpoint: self.endpoint,
+            query: &self.query,
+            headers: &self.headers,
+            body: body.map(|body| body as &mut dyn Read),
+        };
+
+        let resp = self.api.transport.call(request)?;
+        let status = resp.status();
+
+        if !(200..300).contains(&status) {
+            let mut body = String::new();
+            let _ = resp.into_body().read_to_string(&mut body);
+            return Err(Error::from_status(status, body));
+        }
+
+        Ok(resp)
+    }
+}
+
+impl Response {
+    /// Returns the response body.
+    pub(crate) fn into_reader(self) -> Box<dyn Read + Send + Sync> {
+        self.into_body()
+    }
+
+    /// Returns the response body as a [`ByteStream`].
+    pub(crate) fn into_stream(self) -> ByteStream {
+        ByteStream::new(self.into_body())
+    }
+
+    /// Returns an iterator over the JSON values of a streamed response.
+    pub(crate) fn into_json_lines(
+        self,
+    ) -> JsonLines<Box<dyn Read + Send + Sync>> {
+        JsonLines::new(self.into_body())
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{Error, Result};
+use std::fmt::Debug;
+use std::io::{BufRead, BufReader, Read};
+
+/// The default size of the chunks returned by [`ByteStream::chunks`].
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The streamed body of a response, such as the contents of a file.
+///
+/// Errors that happen while the body is being received, including errors
+/// that the daemon reports in the `X-Stream-Error` trailer, are returned by
+/// [`Read::read`] instead of silently ending the stream. I/O errors returned
+/// by the stream can be converted back into an [`Error`] with `From`.
+pub struct ByteStream {
+    reader: Box<dyn Read + Send + Sync>,
+}
+
+impl ByteStream {
+    pub(crate) fn new(reader: Box<dyn Read + Send + Sync>) -> Self {
+        Self { reader }
+    }
+
+    /// Returns an iterator over chunks of the body. The iterator ends after
+    /// the first error.
+    #[must_use]
+    pub fn chunks(self) -> Chunks {
+        Chunks {
+            stream: self,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            failed: false,
+        }
+    }
+}
+
+impl Read for ByteStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Debug for ByteStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ByteStream").finish_non_exhaustive()
+    }
+}
+
+/// An iterator over the chunks of a [`ByteStream`].
+#[derive(Debug)]
+pub struct Chunks {
+    stream: ByteStream,
+    chunk_size: usize,
+    failed: bool,
+}
+
+impl Chunks {
+    /// Sets the maximum size of the returned chunks.
+    #[must_use]
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+}
+
+impl Iterator for Chunks {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let mut chunk = vec![0; self.chunk_size];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return None,
+                Ok(n) => {
+                    chunk.truncate(n);
+                    return Some(Ok(chunk));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+    }
+}
+
+/// Creates the I/O error returned when the daemon reports an error after it
+/// has started sending a response.
+pub(crate) fn stream_error(message: &str) -> std::io::Error {
+    std::io::Error::other(Error::Stream(message.into()))
+}
+
+/// The message of the [`Error::Stream`] returned by [`TrailerErrorReader`],
+/// which cannot read the daemon's own message.
+pub(crate) const UNREADABLE_STREAM_ERROR: &str =
+    "the daemon reported an error while streaming";
+
+/// Reports the failure of a response that announced an `X-Stream-Error`
+/// trailer. ureq cannot read trailers and fails to decode the end of the
+/// body when one is sent, so the message of the trailer is not available
+/// and [`UNREADABLE_STREAM_ERROR`] is returned instead.
+pub(crate) struct TrailerErrorReader<R> {
+    inner: R,
+}
+
+impl<R> TrailerErrorReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+impl<R: Read> Read for TrailerErrorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.inner.read(buf) {
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+                Err(stream_error(UNREADABLE_STREAM_ERROR))
+            }
+            r => r,
+        }
+    }
+}
+
+/// An iterator over a response body that contains one JSON value per line,
+/// as sent by endpoints that stream their results. The iterator ends after
+/// the first error.
+pub(crate) struct JsonLines<R> {
+    reader: BufReader<R>,
+    failed: bool,
+}
+
+impl<R: Read> JsonLines<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            failed: false,
+        }
+    }
+}
+
+impl<R: Read> Iterator for JsonLines<R> {
+    type Item = Result<serde_json::Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let result = match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) if line.trim().is_empty() => continue,
+                Ok(_) => serde_json::from_str(&line).map_err(Error::from),
+                Err(e) => Err(e.into()),
+            };
+
+            self.failed = result.is_err();
+            return Some(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{stream_error, ByteStream};
+    use crate::Error;
+    use std::io::Read;
+
+    #[test]
+    fn test_chunks() {
+        let stream = ByteStream::new(Box::new(&b"Hello world"[..]));
+        let chunks: Vec<Vec<u8>> = stream
+            .chunks()
+            .chunk_size(4)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(chunks, [&b"Hell"[..], b"o wo", b"rld"]);
+    }
+
+    #[test]
+    fn test_chunks_error() {
+        let body = (&b"Hello"[..]).chain(FailingReader);
+        let mut chunks = ByteStream::new(Box::new(body)).chunks();
+
+        assert_eq!(chunks.next().unwrap().unwrap(), b"Hello");
+        match chunks.next() {
+            Some(Err(Error::Stream(msg))) => assert_eq!(msg, "boom"),
+            other => panic!("unexpected chunk: {:?}", other),
+        }
+        assert!(chunks.next().is_none());
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(stream_error("boom"))
+        }
+    }
+}
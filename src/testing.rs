@@ -0,0 +1,672 @@
+//! An in-process mock of the daemon's RPC API, for tests that should run
+//! without a daemon.
+//!
+//! This module is enabled by the `testing` feature. A [`MockServer`] listens
+//! on an ephemeral local port and replies to each `/api/v0/...` endpoint with
+//! the responses scripted for it. The requests it receives are recorded, so
+//! tests can check the arguments and the uploaded multipart bodies.
+//!
+//! ```rust
+//! use ipfsapi::testing::{MockResponse, MockServer};
+//! use serde_json::json;
+//!
+//! let server = MockServer::start();
+//! server.on("block/put", MockResponse::json(&json!({"Key": "bafkqaaa"})));
+//!
+//! let api = server.api();
+//! assert_eq!(api.block_put(b"Hello world")?.to_string(), "bafkqaaa");
+//!
+//! let request = &server.requests()[0];
+//! assert_eq!(request.endpoint, "block/put");
+//! assert_eq!(request.parts().unwrap()[0].body, b"Hello world");
+//! # Ok::<(), ipfsapi::Error>(())
+//! ```
+
+use crate::http1;
+use crate::IpfsApi;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// The path prefix of the API endpoints.
+const API_PREFIX: &str = "/api/v0/";
+
+/// A scripted response of a [`MockServer`].
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    chunks: Vec<Vec<u8>>,
+    chunked: bool,
+    stream_error: Option<String>,
+}
+
+impl MockResponse {
+    /// Creates a response with the given HTTP status and a plain text body.
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".into(), "text/plain".into())],
+            chunks: vec![body.into()],
+            chunked: false,
+            stream_error: None,
+        }
+    }
+
+    /// Creates a successful response with a JSON body.
+    #[must_use]
+    pub fn json(value: &serde_json::Value) -> Self {
+        Self::new(200, value.to_string())
+            .header("Content-Type", "application/json")
+    }
+
+    /// Creates a successful response that streams one JSON value per line,
+    /// as the daemon does for endpoints that report progress.
+    pub fn ndjson<I>(values: I) -> Self
+    where
+        I: IntoIterator<Item = serde_json::Value>,
+    {
+        let lines = values.into_iter().map(|value| format!("{}\n", value));
+
+        Self::stream(lines)
+            .header("Content-Type", "application/json")
+            .header("X-Chunked-Output", "1")
+    }
+
+    /// Creates a successful response whose body is sent with chunked
+    /// encoding, one chunk per item.
+    pub fn stream<I>(chunks: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Vec<u8>>,
+    {
+        Self {
+            chunks: chunks.into_iter().map(Into::into).collect(),
+            chunked: true,
+            ..Self::new(200, "")
+        }
+    }
+
+    /// Creates a failed response with an error body in the daemon's format.
+    #[must_use]
+    pub fn error(message: &str) -> Self {
+        let body = serde_json::json!({
+            "Message": message,
+            "Code": 0,
+            "Type": "error",
+        });
+
+        Self::new(500, body.to_string())
+            .header("Content-Type", "application/json")
+    }
+
+    /// Reports an error in the `X-Stream-Error` trailer after the body has
+    /// been sent, as the daemon does when a streaming response fails.
+    #[must_use]
+    pub fn stream_error(mut self, message: &str) -> Self {
+        self.chunked = true;
+        self.stream_error = Some(message.into());
+        self
+    }
+
+    /// Sets a header of the response, replacing any previous value.
+    #[must_use]
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    fn write_to(&self, stream: &TcpStream) -> std::io::Result<()> {
+        let mut w = BufWriter::new(stream);
+
+        write!(w, "HTTP/1.1 {} {}\r\n", self.status, reason(self.status))?;
+        write!(w, "Connection: close\r\n")?;
+        for (name, value) in &self.headers {
+            write!(w, "{}: {}\r\n", name, value)?;
+        }
+
+        if !self.chunked {
+            let body = self.chunks.concat();
+            write!(w, "Content-Length: {}\r\n\r\n", body.len())?;
+            w.write_all(&body)?;
+            return w.flush();
+        }
+
+        if self.stream_error.is_some() {
+            write!(w, "Trailer: X-Stream-Error\r\n")?;
+        }
+        write!(w, "Transfer-Encoding: chunked\r\n\r\n")?;
+
+        for chunk in self.chunks.iter().filter(|chunk| !chunk.is_empty()) {
+            write!(w, "{:x}\r\n", chunk.len())?;
+            w.write_all(chunk)?;
+            write!(w, "\r\n")?;
+            w.flush()?;
+        }
+
+        write!(w, "0\r\n")?;
+        if let Some(message) = &self.stream_error {
+            write!(w, "X-Stream-Error: {}\r\n", message)?;
+        }
+        write!(w, "\r\n")?;
+        w.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+/// A request received by a [`MockServer`].
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    /// The endpoint, relative to the API path prefix, such as `block/get`.
+    pub endpoint: String,
+
+    /// The full path of the request, such as `/api/v0/block/get`. It
+    /// includes any prefix before the API path.
+    pub path: String,
+
+    /// The decoded query parameters, in order.
+    pub query: Vec<(String, String)>,
+
+    /// The request headers, in order.
+    pub headers: Vec<(String, String)>,
+
+    /// The request body, with any transfer encoding removed.
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    /// Returns the value of the first query parameter with the given name.
+    #[must_use]
+    pub fn query_value(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the values of all `arg` query parameters.
+    #[must_use]
+    pub fn args(&self) -> Vec<&str> {
+        self.query
+            .iter()
+            .filter(|(n, _)| n == "arg")
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    /// Returns the value of the first header with the given name, ignoring
+    /// case.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        http1::header(&self.headers, name)
+    }
+
+    /// Decodes the body as `multipart/form-data`. Returns `None` if the
+    /// request has another content type, or if the body is malformed.
+    #[must_use]
+    pub fn parts(&self) -> Option<Vec<RecordedPart>> {
+        let content_type = self.header("Content-Type")?;
+        let (mime, params) = split_params(content_type);
+        if !mime.eq_ignore_ascii_case("multipart/form-data") {
+            return None;
+        }
+
+        let boundary = find_param(&params, "boundary")?;
+        parse_multipart(&self.body, boundary)
+    }
+}
+
+/// A part of a multipart request body received by a [`MockServer`].
+#[derive(Clone, Debug)]
+pub struct RecordedPart {
+    /// The form field name from `Content-Disposition`.
+    pub name: String,
+
+    /// The filename from `Content-Disposition`, if there is one.
+    pub filename: Option<String>,
+
+    /// The headers of the part, including `Content-Disposition`.
+    pub headers: Vec<(String, String)>,
+
+    /// The contents of the part.
+    pub body: Vec<u8>,
+}
+
+impl RecordedPart {
+    /// Returns the value of the first header with the given name, ignoring
+    /// case.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        http1::header(&self.headers, name)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    routes: HashMap<String, VecDeque<MockResponse>>,
+    requests: Vec<RecordedRequest>,
+}
+
+impl State {
+    /// Returns the next scripted response for an endpoint. The last response
+    /// of each endpoint is repeated for all later requests.
+    fn respond(&mut self, endpoint: &str) -> MockResponse {
+        match self.routes.get_mut(endpoint) {
+            Some(queue) if queue.len() > 1 => queue.pop_front().unwrap(),
+            Some(queue) if !queue.is_empty() => queue[0].clone(),
+            _ => MockResponse::new(404, "404 page not found"),
+        }
+    }
+}
+
+impl std::fmt::Debug for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("State")
+            .field("endpoints", &self.routes.keys())
+            .field("requests", &self.requests.len())
+            .finish()
+    }
+}
+
+/// A local HTTP server that mimics the daemon's RPC API.
+///
+/// The server runs on background threads until it is dropped. Endpoints
+/// without a scripted response reply with `404 Not Found`, like the daemon
+/// does for unknown commands.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Starts a server on an ephemeral port of `127.0.0.1`.
+    ///
+    /// # Panics
+    /// Panics if the server cannot listen on a local port.
+    #[must_use]
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .expect("failed to start the mock server");
+        let addr = listener.local_addr().unwrap();
+
+        let state = Arc::new(Mutex::new(State::default()));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let state = state.clone();
+            let stopped = stopped.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stopped.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let state = state.clone();
+                        std::thread::spawn(move || {
+                            let _ = handle(stream, &state);
+                        });
+                    }
+                }
+            })
+        };
+
+        Self {
+            addr,
+            state,
+            stopped,
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns the address the server is listening on.
+    #[must_use]
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the base URL of the API, such as
+    /// `http://127.0.0.1:43615/api/v0`.
+    #[must_use]
+    pub fn base_url(&self) -> String {
+        format!("http://{}/api/v0", self.addr)
+    }
+
+    /// Returns an API client connected to this server.
+    #[must_use]
+    pub fn api(&self) -> IpfsApi {
+        IpfsApi::builder().base_url(&self.base_url()).build()
+    }
+
+    /// Scripts a response for an endpoint such as `cat` or `block/get`.
+    ///
+    /// Responses scripted for the same endpoint are returned in order, and
+    /// the last one is repeated for all later requests.
+    pub fn on(&self, endpoint: &str, response: MockResponse) {
+        let endpoint = endpoint.trim_start_matches(API_PREFIX);
+        self.state
+            .lock()
+            .unwrap()
+            .routes
+            .entry(endpoint.into())
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Returns the requests received so far, in order.
+    #[must_use]
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+
+        // Wake up the accept loop so that it notices the server is stopped.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Reads a request, records it and writes the scripted response.
+fn handle(stream: TcpStream, state: &Mutex<State>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let (request_line, headers) = http1::read_head(&mut reader)?;
+
+    let target = request_line.split(' ').nth(1).unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    // Unlike a response, a request without a length has no body.
+    let mut body = Vec::new();
+    if http1::header(&headers, "Transfer-Encoding").is_some()
+        || http1::header(&headers, "Content-Length").is_some()
+    {
+        http1::body_reader(reader, &headers)?.read_to_end(&mut body)?;
+    }
+
+    // Clients behind a reverse proxy may add a prefix to the API path.
+    let endpoint = path
+        .find(API_PREFIX)
+        .map(|start| &path[start + API_PREFIX.len()..]);
+    let response = match endpoint {
+        Some(endpoint) => {
+            let mut state = state.lock().unwrap();
+            state.requests.push(RecordedRequest {
+                endpoint: endpoint.into(),
+                path: path.into(),
+                query: decode_query(query),
+                headers,
+                body,
+            });
+            state.respond(endpoint)
+        }
+        None => MockResponse::new(404, "404 page not found"),
+    };
+
+    response.write_to(&stream)
+}
+
+/// Decodes `application/x-www-form-urlencoded` query parameters.
+fn decode_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Splits a header value such as `form-data; name="file"` into its main
+/// value and its parameters. Quoted parameter values are unescaped.
+fn split_params(value: &str) -> (&str, Vec<(String, String)>) {
+    let (main, mut rest) = value.split_once(';').unwrap_or((value, ""));
+    let mut params = Vec::new();
+
+    loop {
+        rest = rest.trim_start_matches([' ', ';']);
+        let Some((name, after)) = rest.split_once('=') else {
+            break;
+        };
+
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => unquote(quoted),
+            None => {
+                let end = after.find(';').unwrap_or(after.len());
+                (after[..end].trim().to_string(), &after[end..])
+            }
+        };
+
+        params.push((name.trim().to_ascii_lowercase(), value));
+        rest = after;
+    }
+
+    (main.trim(), params)
+}
+
+/// Reads a quoted string up to its closing quote, and returns its unescaped
+/// value and the text after it.
+fn unquote(s: &str) -> (String, &str) {
+    let mut value = String::new();
+    let mut chars = s.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (value, &s[i + 1..]),
+            '\\' => {
+                if let Some((_, c)) = chars.next() {
+                    value.push(c);
+                }
+            }
+            c => value.push(c),
+        }
+    }
+
+    (value, "")
+}
+
+fn find_param<'a>(
+    params: &'a [(String, String)],
+    name: &str,
+) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Splits a `multipart/form-data` body into its parts.
+fn parse_multipart(body: &[u8], boundary: &str) -> Option<Vec<RecordedPart>> {
+    let delimiter = format!("\r\n--{}", boundary).into_bytes();
+
+    // The first boundary is not preceded by a line break.
+    let mut rest = body.strip_prefix(&delimiter[2..])?;
+    let mut parts = Vec::new();
+
+    loop {
+        if rest.starts_with(b"--") {
+            return Some(parts);
+        }
+        rest = rest.strip_prefix(b"\r\n")?;
+
+        let end = find(rest, &delimiter)?;
+        let (part, after) = rest.split_at(end);
+        rest = &after[delimiter.len()..];
+
+        let head_end = find(part, b"\r\n\r\n")?;
+        let head = std::str::from_utf8(&part[..head_end]).ok()?;
+        let headers: Vec<(String, String)> = head
+            .split("\r\n")
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().into(), value.trim().into()))
+            .collect();
+
+        let disposition = http1::header(&headers, "Content-Disposition")?;
+        let (_, params) = split_params(disposition);
+
+        parts.push(RecordedPart {
+            name: find_param(&params, "name")?.into(),
+            filename: find_param(&params, "filename").map(Into::into),
+            headers,
+            body: part[head_end + 4..].to_vec(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MockResponse, MockServer};
+    use crate::multipart::{Multipart, Part};
+    use crate::Error;
+    use serde_json::json;
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn test_unknown_endpoint() {
+        let server = MockServer::start();
+
+        match server.api().shutdown() {
+            Err(Error::Status { code, body }) => {
+                assert_eq!(code, 404);
+                assert_eq!(body, "404 page not found");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(server.requests()[0].endpoint, "shutdown");
+    }
+
+    #[test]
+    fn test_scripted_responses() {
+        let server = MockServer::start();
+        server.on("name/resolve", MockResponse::error("not found"));
+        server.on("name/resolve", MockResponse::json(&json!({"Path": "/a"})));
+
+        let api = server.api();
+        match api.name_resolve("example.com") {
+            Err(Error::Api { message, .. }) => assert_eq!(message, "not found"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(api.name_resolve("example.com").unwrap(), "/a");
+        assert_eq!(api.name_resolve("example.com").unwrap(), "/a");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].args(), ["example.com"]);
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let server = MockServer::start();
+        server.on(
+            "cat",
+            MockResponse::ndjson([json!({"Bytes": 1}), json!({"Bytes": 2})]),
+        );
+
+        let stream = server.api().cat("QmHash").unwrap();
+        let lines: Vec<serde_json::Value> = BufReader::new(stream)
+            .lines()
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect();
+
+        assert_eq!(lines, [json!({"Bytes": 1}), json!({"Bytes": 2})]);
+    }
+
+    #[test]
+    fn test_stream_error() {
+        let server = MockServer::start();
+        server.on(
+            "cat",
+            MockResponse::stream(["Hello "]).stream_error("deadline exceeded"),
+        );
+
+        let chunks: Vec<_> =
+            server.api().cat("QmHash").unwrap().chunks().collect();
+        assert_eq!(chunks[0].as_ref().unwrap(), b"Hello ");
+
+        // ureq cannot read the trailer, so its message is lost.
+        match chunks.last() {
+            Some(Err(Error::Stream(msg))) => {
+                assert_eq!(msg, crate::stream::UNREADABLE_STREAM_ERROR)
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_recorded_parts() {
+        let server = MockServer::start();
+        server.on("block/put", MockResponse::json(&json!({"Key": "QmKey"})));
+
+        let mut body = Multipart::new();
+        body.add_part(Part::new("data", &b"a\r\nb"[..]));
+        body.add_part(Part::new("file", &b""[..]).filename("dir/\"x\".txt"));
+
+        let api = server.api();
+        api.post("block/put")
+            .set("Content-Type", &body.content_type())
+            .send(body.into_reader())
+            .unwrap();
+
+        let request = &server.requests()[0];
+        let parts = request.parts().unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name, "data");
+        assert_eq!(parts[0].body, b"a\r\nb");
+        assert_eq!(parts[1].filename.as_deref(), Some("dir/\"x\".txt"));
+        assert!(parts[1].body.is_empty());
+        assert!(request.header("Transfer-Encoding").is_some());
+    }
+}
@@ -0,0 +1,327 @@
+//! Pluggable transports that deliver API requests to the daemon.
+//!
+//! [`IpfsApi`](crate::IpfsApi) hands every request to a [`Transport`]. The
+//! default is [`HttpTransport`], which talks HTTP over TCP, but any other
+//! implementation can be set with
+//! [`IpfsApiBuilder::transport`](crate::IpfsApiBuilder::transport), for
+//! example to run against a fake backend in tests.
+//!
+//! ```rust
+//! use ipfsapi::transport::{Request, Response, Transport};
+//! use ipfsapi::IpfsApi;
+//!
+//! #[derive(Debug)]
+//! struct FakeDaemon;
+//!
+//! impl Transport for FakeDaemon {
+//!     fn call(&self, request: Request<'_>) -> ipfsapi::Result<Response> {
+//!         match request.endpoint {
+//!             "version" => Ok(Response::new(200, &br#"{"Version":"0.30.0"}"#[..])),
+//!             _ => Ok(Response::new(404, &b"404 page not found"[..])),
+//!         }
+//!     }
+//! }
+//!
+//! let api = IpfsApi::builder().transport(FakeDaemon).build();
+//! assert_eq!(api.version()?.version(), Some("0.30.0"));
+//! # Ok::<(), ipfsapi::Error>(())
+//! ```
+
+use crate::stream::TrailerErrorReader;
+use crate::Result;
+use std::fmt::Debug;
+use std::io::Read;
+
+/// Delivers API requests to the daemon.
+pub trait Transport: Debug + Send + Sync {
+    /// Sends a request and returns the response.
+    ///
+    /// Responses with an unsuccessful status should be returned as `Ok`. The
+    /// status is checked by the caller, which also decodes error bodies.
+    /// Failures to deliver the request should be returned as
+    /// [`Error::Transport`](crate::Error::Transport).
+    fn call(&self, request: Request<'_>) -> Result<Response>;
+}
+
+/// A request to an API endpoint.
+pub struct Request<'a> {
+    /// The endpoint, relative to the API path prefix, such as `block/get`.
+    pub endpoint: &'a str,
+
+    /// The query parameters, in order. Parameters such as `arg` can be
+    /// repeated.
+    pub query: &'a [(String, String)],
+
+    /// The headers, including the default headers and the credentials of
+    /// the [`IpfsApi`](crate::IpfsApi).
+    pub headers: &'a [(String, String)],
+
+    /// The request body, if there is one.
+    pub body: Option<&'a mut dyn Read>,
+}
+
+impl Request<'_> {
+    /// Returns the value of the first query parameter with the given name.
+    #[must_use]
+    pub fn query_value(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the value of the first header with the given name, ignoring
+    /// case.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl Debug for Request<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let headers: Vec<&str> =
+            self.headers.iter().map(|(n, _)| n.as_str()).collect();
+
+        f.debug_struct("Request")
+            .field("endpoint", &self.endpoint)
+            .field("query", &self.query)
+            .field("headers", &headers)
+            .field("body", &self.body.is_some())
+            .finish()
+    }
+}
+
+/// A response from the daemon.
+pub struct Response {
+    status: u16,
+    body: Box<dyn Read + Send + Sync>,
+}
+
+impl Response {
+    /// Creates a response with the given HTTP status and body.
+    pub fn new(status: u16, body: impl Read + Send + Sync + 'static) -> Self {
+        Self {
+            status,
+            body: Box::new(body),
+        }
+    }
+
+    /// Returns the HTTP status of the response.
+    #[must_use]
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Returns the response body.
+    #[must_use]
+    pub fn into_body(self) -> Box<dyn Read + Send + Sync> {
+        self.body
+    }
+}
+
+impl Debug for Response {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Response")
+            .field("status", &self.status)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The default transport, which sends requests over HTTP or HTTPS using
+/// `ureq`.
+#[derive(Clone, Debug)]
+pub struct HttpTransport {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl HttpTransport {
+    /// Creates a transport for the API at the given base URL, such as
+    /// `http://127.0.0.1:5001/api/v0`.
+    #[must_use]
+    pub fn new(base_url: &str) -> Self {
+        Self::with_agent(base_url, ureq::Agent::new())
+    }
+
+    pub(crate) fn with_agent(base_url: &str, agent: ureq::Agent) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').into(),
+            agent,
+        }
+    }
+
+    /// Returns the full URL of an API endpoint.
+    fn url(&self, endpoint: &str) -> String {
+        format!("{}/{}", self.base_url, endpoint)
+    }
+}
+
+impl Transport for HttpTransport {
+    fn call(&self, request: Request<'_>) -> Result<Response> {
+        let mut req = self.agent.post(&self.url(request.endpoint));
+
+        for (name, value) in request.query {
+            req = req.query(name, value);
+        }
+
+        for (name, value) in request.headers {
+            req = req.set(name, value);
+        }
+
+        let result = match request.body {
+            Some(body) => req.send(body),
+            None => req.call(),
+        };
+
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(ureq::Error::Status(status, resp)) => {
+                return Ok(Response::new(status, resp.into_reader()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let status = resp.status();
+        let trailer = resp.header("Trailer").unwrap_or_default();
+        if trailer.to_ascii_lowercase().contains("x-stream-error") {
+            let reader = TrailerErrorReader::new(resp.into_reader());
+            return Ok(Response::new(status, reader));
+        }
+
+        Ok(Response::new(status, resp.into_reader()))
+    }
+}
+
+/// Encodes query parameters as `application/x-www-form-urlencoded`.
+#[cfg(any(unix, feature = "async"))]
+pub(crate) fn encode_query(query: &[(String, String)]) -> String {
+    let mut out = String::new();
+
+    for (name, value) in query {
+        if !out.is_empty() {
+            out.push('&');
+        }
+        percent_encode(&mut out, name);
+        out.push('=');
+        percent_encode(&mut out, value);
+    }
+
+    out
+}
+
+/// Appends `s` to `out`, percent-encoding everything but the unreserved
+/// characters of RFC 3986.
+pub(crate) fn percent_encode(out: &mut String, s: &str) {
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HttpTransport, Request, Response, Transport};
+    use crate::cid::Cid;
+    use crate::{Error, IpfsApi};
+    use std::sync::{Arc, Mutex};
+
+    /// Records the requests it receives and replies with a canned response.
+    #[derive(Debug)]
+    struct Recorder {
+        status: u16,
+        body: &'static [u8],
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Transport for Recorder {
+        fn call(&self, request: Request<'_>) -> crate::Result<Response> {
+            let mut line = request.endpoint.to_string();
+            for (name, value) in request.query {
+                line.push_str(&format!(" {}={}", name, value));
+            }
+            if let Some(auth) = request.header("Authorization") {
+                line.push_str(&format!(" [{}]", auth));
+            }
+            if let Some(body) = request.body {
+                let mut text = String::new();
+                body.read_to_string(&mut text)?;
+                line.push_str(&format!(" <{}>", text.len()));
+            }
+
+            self.requests.lock().unwrap().push(line);
+            Ok(Response::new(self.status, self.body))
+        }
+    }
+
+    fn api(
+        status: u16,
+        body: &'static [u8],
+    ) -> (IpfsApi, Arc<Mutex<Vec<String>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let transport = Recorder {
+            status,
+            body,
+            requests: requests.clone(),
+        };
+        let api = IpfsApi::builder()
+            .bearer_auth("token")
+            .transport(transport)
+            .build();
+        (api, requests)
+    }
+
+    #[test]
+    fn test_recorded_request() {
+        let cid: Cid = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u"
+            .parse()
+            .unwrap();
+        let (api, requests) = api(
+            200,
+            br#"{"Pins":["QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u"]}"#,
+        );
+        assert_eq!(api.pin_add(&cid).unwrap(), std::slice::from_ref(&cid));
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(
+            requests[0],
+            format!(
+                "pin/add arg={} recursive=true progress=false [Bearer token]",
+                cid
+            )
+        );
+    }
+
+    #[test]
+    fn test_error_status() {
+        let body = br#"{"Message":"invalid path","Code":0,"Type":"error"}"#;
+        let (api, _) = api(500, body);
+
+        match api.name_resolve("not-a-name") {
+            Err(Error::Api { message, .. }) => {
+                assert_eq!(message, "invalid path")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_http_transport_url() {
+        let transport = HttpTransport::new("https://example.com/ipfs/api/v0/");
+        assert_eq!(
+            transport.url("block/get"),
+            "https://example.com/ipfs/api/v0/block/get"
+        );
+    }
+}
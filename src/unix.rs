@@ -0,0 +1,254 @@
+// A minimal HTTP/1.1 client for talking to the daemon over a Unix domain
+// socket, which ureq does not support.
+
+use crate::http1;
+use crate::transport::{encode_query, Request, Response, Transport};
+use crate::{Error, Result};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Sends requests over a Unix domain socket.
+#[derive(Clone, Debug)]
+pub(crate) struct UnixTransport {
+    path: PathBuf,
+    host: String,
+    prefix: String,
+    timeout_read: Option<Duration>,
+    user_agent: Option<String>,
+}
+
+fn transport(err: std::io::Error) -> Error {
+    Error::Transport(Box::new(err))
+}
+
+fn bad_response(msg: &str) -> Error {
+    Error::Transport(msg.into())
+}
+
+impl UnixTransport {
+    /// Creates a transport for the socket at `path`. The host and the path
+    /// prefix of the requests are taken from `base_url`.
+    pub(crate) fn new(
+        path: PathBuf,
+        base_url: &str,
+        timeout_read: Option<Duration>,
+        user_agent: Option<String>,
+    ) -> Self {
+        let (host, prefix) = split_url(base_url);
+
+        Self {
+            path,
+            host: host.into(),
+            prefix: prefix.trim_end_matches('/').into(),
+            timeout_read,
+            user_agent,
+        }
+    }
+}
+
+impl Transport for UnixTransport {
+    fn call(&self, request: Request<'_>) -> Result<Response> {
+        let mut target = format!("{}/{}", self.prefix, request.endpoint);
+        if !request.query.is_empty() {
+            target.push('?');
+            target.push_str(&encode_query(request.query));
+        }
+
+        let stream = UnixStream::connect(&self.path).map_err(transport)?;
+        stream
+            .set_read_timeout(self.timeout_read)
+            .map_err(transport)?;
+
+        self.write_request(&stream, &target, request.headers, request.body)
+            .map_err(transport)?;
+
+        let mut reader = BufReader::new(stream);
+        let (status, resp_headers) = read_head(&mut reader)?;
+
+        let body =
+            http1::body_reader(reader, &resp_headers).map_err(transport)?;
+
+        Ok(Response::new(status, body))
+    }
+}
+
+impl UnixTransport {
+    fn write_request(
+        &self,
+        stream: &UnixStream,
+        target: &str,
+        headers: &[(String, String)],
+        body: Option<&mut dyn Read>,
+    ) -> std::io::Result<()> {
+        let mut w = BufWriter::new(stream);
+
+        write!(w, "POST {} HTTP/1.1\r\n", target)?;
+        write!(w, "Host: {}\r\n", self.host)?;
+        write!(w, "Connection: close\r\n")?;
+
+        if let Some(user_agent) = &self.user_agent {
+            write!(w, "User-Agent: {}\r\n", user_agent)?;
+        }
+
+        for (name, value) in headers {
+            write!(w, "{}: {}\r\n", name, value)?;
+        }
+
+        match body {
+            None => write!(w, "Content-Length: 0\r\n\r\n")?,
+            Some(body) => {
+                write!(w, "Transfer-Encoding: chunked\r\n\r\n")?;
+
+                let mut buf = vec![0; 64 * 1024];
+                loop {
+                    let n = body.read(&mut buf)?;
+                    if n == 0 {
+                        break;
+                    }
+                    write!(w, "{:x}\r\n", n)?;
+                    w.write_all(&buf[..n])?;
+                    write!(w, "\r\n")?;
+                }
+
+                write!(w, "0\r\n\r\n")?;
+            }
+        }
+
+        w.flush()
+    }
+}
+
+/// Reads the status line and the headers of a response.
+fn read_head(
+    reader: &mut impl BufRead,
+) -> Result<(u16, Vec<(String, String)>)> {
+    let (status_line, headers) = http1::read_head(reader).map_err(transport)?;
+    let status = status_line
+        .split(' ')
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| bad_response("invalid HTTP status line"))?;
+
+    Ok((status, headers))
+}
+
+/// Splits a URL into its host and path.
+fn split_url(url: &str) -> (&str, &str) {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+
+    match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_url;
+    use crate::{Error, IpfsApi};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+    use std::thread::JoinHandle;
+
+    /// Serves a single canned response on a Unix socket, and returns the head
+    /// of the request that was received.
+    fn serve(
+        name: &str,
+        response: &'static [u8],
+    ) -> (PathBuf, JoinHandle<String>) {
+        let dir = std::env::temp_dir().join(format!(
+            "ipfsapi-unix-{}-{}",
+            std::process::id(),
+            name
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("api.sock");
+        let _ = std::fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut head = String::new();
+            while !head.ends_with("\r\n\r\n") {
+                reader.read_line(&mut head).unwrap();
+            }
+
+            (&stream).write_all(response).unwrap();
+            std::fs::remove_dir_all(&dir).unwrap();
+            head
+        });
+
+        (path, server)
+    }
+
+    #[test]
+    fn test_split_url() {
+        assert_eq!(
+            split_url("http://localhost/api/v0"),
+            ("localhost", "/api/v0")
+        );
+        assert_eq!(split_url("http://localhost"), ("localhost", "/"));
+    }
+
+    #[test]
+    fn test_unix_socket_cat() {
+        let (path, server) = serve(
+            "cat",
+            b"HTTP/1.1 200 OK\r\n\
+              Transfer-Encoding: chunked\r\n\r\n\
+              6\r\nHello \r\n6\r\nWorld\n\r\n0\r\n\r\n",
+        );
+
+        let addr = format!("/unix{}", path.display());
+        let api = IpfsApi::builder()
+            .multiaddr(&addr)
+            .unwrap()
+            .bearer_auth("secret-token")
+            .build();
+        let mut stream = api
+            .cat("QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u")
+            .unwrap();
+        let mut bytes = Vec::new();
+        stream.read_to_end(&mut bytes).unwrap();
+
+        assert_eq!(bytes, b"Hello World\n");
+        let head = server.join().unwrap();
+        assert!(head.starts_with(
+            "POST /api/v0/cat?arg=QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u HTTP/1.1\r\n"
+        ));
+        assert!(head.contains("\r\nAuthorization: Bearer secret-token\r\n"));
+    }
+
+    #[test]
+    fn test_unix_socket_stream_error() {
+        let (path, server) = serve(
+            "stream-error",
+            b"HTTP/1.1 200 OK\r\n\
+              Trailer: X-Stream-Error\r\n\
+              Transfer-Encoding: chunked\r\n\r\n\
+              6\r\nHello \r\n0\r\n\
+              X-Stream-Error: context deadline exceeded\r\n\r\n",
+        );
+
+        let api = IpfsApi::builder().unix_socket(path).build();
+        let chunks: Vec<_> = api
+            .cat("QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u")
+            .unwrap()
+            .chunks()
+            .collect();
+        server.join().unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].as_ref().unwrap(), b"Hello ");
+        match &chunks[1] {
+            Err(Error::Stream(msg)) => {
+                assert_eq!(msg, "context deadline exceeded")
+            }
+            other => panic!("unexpected chunk: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,447 @@
+//! An offline UnixFS importer, which computes the CID of a file without a
+//! daemon.
+//!
+//! The importer splits the file into fixed-size chunks, and links them in
+//! a balanced DAG of dag-pb nodes, like `ipfs add`. With the default
+//! options, the CIDs match those of `ipfs add --only-hash`, and with
+//! [`cid_version(Version::V1)`](ImportOptions::cid_version) those of
+//! `ipfs add --only-hash --cid-version=1`.
+//!
+//! ```rust
+//! use ipfsapi::cid::Version;
+//! use ipfsapi::unixfs::{self, ImportOptions};
+//!
+//! let cid = unixfs::hash(&b"hello world\n"[..], &ImportOptions::new())?;
+//! assert_eq!(cid.to_string(), "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o");
+//!
+//! let opts = ImportOptions::new().cid_version(Version::V1);
+//! let cid = unixfs::hash(&b"hello world\n"[..], &opts)?;
+//! assert_eq!(
+//!     cid.to_string(),
+//!     "bafkreifjjcie6lypi6ny7amxnfftagclbuxndqonfipmb64f2km2devei4"
+//! );
+//! # Ok::<(), ipfsapi::Error>(())
+//! ```
+
+use crate::cid::{Cid, Multihash, Version, DAG_PB, RAW};
+use crate::varint;
+use crate::Result;
+use std::io::Read;
+
+/// The UnixFS type of raw data nodes. Like Kubo, the importer uses it for
+/// every leaf but the first one, when raw leaves are disabled.
+const RAW_TYPE: u64 = 0;
+
+/// The UnixFS type of file nodes.
+const FILE_TYPE: u64 = 2;
+
+/// Options of the UnixFS importer. The defaults match those of Kubo.
+///
+/// ```rust
+/// use ipfsapi::cid::Version;
+/// use ipfsapi::unixfs::ImportOptions;
+///
+/// // Kubo's `unixfs-v1-2025` profile.
+/// let opts = ImportOptions::new()
+///     .cid_version(Version::V1)
+///     .chunk_size(1024 * 1024)
+///     .max_links(1024);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ImportOptions {
+    cid_version: Version,
+    raw_leaves: Option<bool>,
+    chunk_size: usize,
+    max_links: usize,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            cid_version: Version::V0,
+            raw_leaves: None,
+            chunk_size: 256 * 1024,
+            max_links: 174,
+        }
+    }
+}
+
+impl ImportOptions {
+    /// Creates options that match the defaults of Kubo: CIDv0, chunks of
+    /// 256 KiB and up to 174 links per node.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the CID version. Version 1 also enables raw leaves, unless they
+    /// are disabled with [`raw_leaves`](Self::raw_leaves).
+    #[must_use]
+    pub fn cid_version(mut self, version: Version) -> Self {
+        self.cid_version = version;
+        self
+    }
+
+    /// Stores the leaves of the DAG as raw blocks instead of UnixFS nodes.
+    /// Raw leaves always have a CIDv1, even when the other nodes have a
+    /// CIDv0.
+    #[must_use]
+    pub fn raw_leaves(mut self, raw_leaves: bool) -> Self {
+        self.raw_leaves = Some(raw_leaves);
+        self
+    }
+
+    /// Sets the size of the chunks, like `--chunker=size-<n>`.
+    #[must_use]
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size.max(1);
+        self
+    }
+
+    /// Sets the maximum number of links of a node. It must be at least 2.
+    #[must_use]
+    pub fn max_links(mut self, max_links: usize) -> Self {
+        self.max_links = max_links.max(2);
+        self
+    }
+
+    fn raw_leaves_enabled(&self) -> bool {
+        self.raw_leaves.unwrap_or(self.cid_version == Version::V1)
+    }
+}
+
+/// A link to a child of a file node.
+struct Link {
+    cid: Cid,
+
+    /// The size of the blocks of the child and its descendants.
+    tsize: u64,
+
+    /// The number of bytes of the file under the child.
+    file_size: u64,
+}
+
+/// Builds the DAG of a file, bottom-up. The links of each level of the tree
+/// are kept until the level is full, so only one branch is in memory.
+struct Builder<'a, F> {
+    opts: &'a ImportOptions,
+    on_block: F,
+    levels: Vec<Vec<Link>>,
+}
+
+impl<F: FnMut(&Cid, &[u8]) -> Result<()>> Builder<'_, F> {
+    /// Adds a chunk of the file, as a raw block or as a UnixFS node of the
+    /// given type.
+    fn add_leaf(&mut self, data: &[u8], unixfs_type: u64) -> Result<()> {
+        let link = if self.opts.raw_leaves_enabled() {
+            let cid = Cid::new_v1(RAW, Multihash::sha2_256(data));
+            (self.on_block)(&cid, data)?;
+            Link {
+                cid,
+                tsize: data.len() as u64,
+                file_size: data.len() as u64,
+            }
+        } else {
+            let mut unixfs = Vec::new();
+            field_varint(&mut unixfs, 1, unixfs_type);
+            if !data.is_empty() {
+                field_bytes(&mut unixfs, 2, data);
+            }
+            field_varint(&mut unixfs, 3, data.len() as u64);
+            self.add_node(&[], &unixfs, data.len() as u64)?
+        };
+
+        self.push(0, link)
+    }
+
+    /// Adds a link to a level, and first moves the full level into a node
+    /// of the level above.
+    fn push(&mut self, level: usize, link: Link) -> Result<()> {
+        if self.levels.len() == level {
+            self.levels.push(Vec::new());
+        }
+        if self.levels[level].len() == self.opts.max_links {
+            let links = std::mem::take(&mut self.levels[level]);
+            let parent = self.add_parent(&links)?;
+            self.push(level + 1, parent)?;
+        }
+
+        self.levels[level].push(link);
+        Ok(())
+    }
+
+    /// Links the remaining nodes of every level, and returns the root.
+    fn finish(mut self) -> Result<Cid> {
+        let mut level = 0;
+        loop {
+            let links = std::mem::take(&mut self.levels[level]);
+            if level + 1 == self.levels.len() && links.len() == 1 {
+                return Ok(links.into_iter().next().unwrap().cid);
+            }
+
+            let parent = self.add_parent(&links)?;
+            self.push(level + 1, parent)?;
+            level += 1;
+        }
+    }
+
+    fn add_parent(&mut self, links: &[Link]) -> Result<Link> {
+        let file_size = links.iter().map(|link| link.file_size).sum();
+
+        let mut unixfs = Vec::new();
+        field_varint(&mut unixfs, 1, FILE_TYPE);
+        field_varint(&mut unixfs, 3, file_size);
+        for link in links {
+            field_varint(&mut unixfs, 4, link.file_size);
+        }
+
+        self.add_node(links, &unixfs, file_size)
+    }
+
+    /// Encodes a dag-pb node, and returns a link to it.
+    fn add_node(
+        &mut self,
+        links: &[Link],
+        data: &[u8],
+        file_size: u64,
+    ) -> Result<Link> {
+        // The links come before the data in the canonical encoding. Kubo
+        // always writes the name of a link, even when it is empty.
+        let mut node = Vec::new();
+        for link in links {
+            let mut encoded = Vec::new();
+            field_bytes(&mut encoded, 1, &link.cid.to_bytes());
+            field_bytes(&mut encoded, 2, b"");
+            field_varint(&mut encoded, 3, link.tsize);
+            field_bytes(&mut node, 2, &encoded);
+        }
+        field_bytes(&mut node, 1, data);
+
+        let hash = Multihash::sha2_256(&node);
+        let cid = match self.opts.cid_version {
+            Version::V0 => Cid::new_v0(hash)?,
+            Version::V1 => Cid::new_v1(DAG_PB, hash),
+        };
+        (self.on_block)(&cid, &node)?;
+
+        let tsize =
+            node.len() as u64 + links.iter().map(|l| l.tsize).sum::<u64>();
+        Ok(Link {
+            cid,
+            tsize,
+            file_size,
+        })
+    }
+}
+
+fn field_varint(out: &mut Vec<u8>, field: u64, value: u64) {
+    varint::encode(field << 3, out);
+    varint::encode(value, out);
+}
+
+fn field_bytes(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    varint::encode(field << 3 | 2, out);
+    varint::encode(bytes.len() as u64, out);
+    out.extend_from_slice(bytes);
+}
+
+/// Computes the CID of a file, like `ipfs add --only-hash`.
+pub fn hash(reader: impl Read, opts: &ImportOptions) -> Result<Cid> {
+    import(reader, opts, |_, _| Ok(()))
+}
+
+/// Imports a file, and returns the CID of its root.
+///
+/// Every block of the DAG is passed to `on_block` with its CID, children
+/// before their parents, so the blocks can be written to a
+/// [`CarWriter`](crate::car::CarWriter) or put with
+/// [`IpfsApi::block_put`](crate::IpfsApi::block_put).
+pub fn import(
+    mut reader: impl Read,
+    opts: &ImportOptions,
+    on_block: impl FnMut(&Cid, &[u8]) -> Result<()>,
+) -> Result<Cid> {
+    let mut builder = Builder {
+        opts,
+        on_block,
+        levels: Vec::new(),
+    };
+
+    let mut chunk = vec![0; opts.chunk_size];
+    loop {
+        let len = read_chunk(&mut reader, &mut chunk)?;
+        // An empty file still has one empty leaf. The first leaf is a file
+        // node, since it is the root of a file of one chunk, and the others
+        // are raw data nodes.
+        if builder.levels.is_empty() {
+            builder.add_leaf(&chunk[..len], FILE_TYPE)?;
+        } else if len > 0 {
+            builder.add_leaf(&chunk[..len], RAW_TYPE)?;
+        }
+        if len < chunk.len() {
+            break;
+        }
+    }
+
+    builder.finish()
+}
+
+/// Fills the buffer from the reader, unless the reader ends first. Returns
+/// the number of bytes read.
+fn read_chunk(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash, import, ImportOptions};
+    use crate::cid::{Cid, Version};
+
+    fn v1() -> ImportOptions {
+        ImportOptions::new().cid_version(Version::V1)
+    }
+
+    #[test]
+    fn test_single_chunk() {
+        // The CIDs of `ipfs add --only-hash`.
+        for (data, opts, expected) in [
+            (
+                &b"hello world\n"[..],
+                ImportOptions::new(),
+                "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o",
+            ),
+            (
+                b"hello world\n",
+                v1(),
+                "bafkreifjjcie6lypi6ny7amxnfftagclbuxndqonfipmb64f2km2devei4",
+            ),
+            (
+                b"",
+                ImportOptions::new(),
+                "QmbFMke1KXqnYyBBWxB74N4c5SBnJMVAiMNRcGu6x1AwQH",
+            ),
+            (
+                b"",
+                v1(),
+                "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku",
+            ),
+        ] {
+            assert_eq!(hash(data, &opts).unwrap().to_string(), expected);
+        }
+
+        // Without raw leaves, CIDv1 names the same node as CIDv0.
+        let opts = v1().raw_leaves(false);
+        let cid = hash(&b"hello world\n"[..], &opts).unwrap();
+        assert_eq!(
+            cid.to_v0().unwrap().to_string(),
+            "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o"
+        );
+    }
+
+    #[test]
+    fn test_balanced_layout() {
+        // Ten chunks of 4 bytes under nodes of up to 3 links make a tree of
+        // depth 3: the root links to nodes of 9 and 1 chunks.
+        let data: Vec<u8> = (0..40).collect();
+        let opts = ImportOptions::new().chunk_size(4).max_links(3);
+
+        let mut blocks = Vec::new();
+        let root = import(&data[..], &opts, |cid, block| {
+            blocks.push((cid.clone(), block.to_vec()));
+            Ok(())
+        })
+        .unwrap();
+
+        // 10 leaves, 4 nodes of depth 1, 2 of depth 2 and the root.
+        assert_eq!(blocks.len(), 17);
+        let (last, block) = blocks.last().unwrap();
+        assert_eq!(*last, root);
+        assert_eq!(
+            root.to_string(),
+            "QmaZ3Uoqq3gg75LGn33wKFuXiZayeLdfn4jj5s1pSC1m6v"
+        );
+        assert_eq!(hash(&data[..], &opts).unwrap(), root);
+
+        // The root's UnixFS data: a file of 40 bytes, made of 36 and 4.
+        let unixfs = [0x08, 0x02, 0x18, 40, 0x20, 36, 0x20, 4];
+        assert!(block.ends_with(&unixfs));
+        let links = block.windows(3).filter(|w| w == &[0x12, 0x00, 0x18]);
+        assert_eq!(links.count(), 2);
+
+        // The tree only depends on the chunks.
+        let reader = std::io::Read::chain(&data[..20], &data[20..]);
+        assert_eq!(hash(reader, &opts).unwrap(), root);
+        let other = ImportOptions::new().chunk_size(5).max_links(3);
+        assert_ne!(hash(&data[..], &other).unwrap(), root);
+    }
+
+    #[test]
+    fn test_large_file() {
+        // 1 MiB of zeros is four full chunks under one node. The first leaf
+        // is a file node and the others are raw data nodes, so it differs
+        // from the three others.
+        let data = vec![0; 1024 * 1024];
+        let mut blocks = Vec::<Cid>::new();
+        let root = import(&data[..], &ImportOptions::new(), |cid, _| {
+            blocks.push(cid.clone());
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(blocks.len(), 5);
+        assert_ne!(blocks[0], blocks[1]);
+        assert!(blocks[1..4].iter().all(|cid| *cid == blocks[1]));
+        assert_eq!(
+            root.to_string(),
+            "QmXumTucKRXL3EEE1WS1SET9cTxKmdtG9xAwnPbuzXqSiW"
+        );
+
+        // With raw leaves, all four leaves are the same block.
+        let v1_root = hash(&data[..], &v1()).unwrap();
+        assert_eq!(
+            v1_root.to_string(),
+            "bafybeiggzq4ryi7hscq5hzvzcnk4urnxt3asp37dhgvnjilf7exskximla"
+        );
+        let opts = v1().raw_leaves(false);
+        assert_eq!(
+            hash(&data[..], &opts).unwrap().to_string(),
+            "bafybeigpeo5nxpgxtgif2tyzzbjrlokm7tbuccpo73pd7gkfro6fzk3vce"
+        );
+    }
+
+    #[test]
+    fn test_two_levels() {
+        // 175 chunks and one byte: the root links to a full node of 174
+        // chunks and to a node of the last 2.
+        let len = 175 * 256 * 1024 + 1;
+        let data = std::io::Read::take(std::io::repeat(0), len);
+        let mut blocks = 0;
+        let root = import(data, &ImportOptions::new(), |_, _| {
+            blocks += 1;
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(blocks, 176 + 2 + 1);
+        assert_eq!(
+            root.to_string(),
+            "QmT6x3SCJnwkGVGJEu9wocF3QVkz1cVdD8o63zsbemU2sf"
+        );
+
+        let data = std::io::Read::take(std::io::repeat(0), len);
+        assert_eq!(
+            hash(data, &v1()).unwrap().to_string(),
+            "bafybeicyowx3udu4hzfyo2ekfduhsfhirbv5j2uhcuyz4zh53vrbx2jdfa"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Unsigned varints, as used by the multiformats: little-endian groups of 7
+//! bits, where the high bit of each byte marks that another byte follows.
+
+use std::io::Read;
+
+/// The longest varint allowed by the multiformats, which is enough for 63
+/// bits.
+const MAX_LEN: usize = 9;
+
+/// Appends the varint encoding of `value` to `out`.
+pub(crate) fn encode(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Decodes a varint at the start of `buf`, and returns its value and length.
+/// Returns `None` if the varint is truncated, too long or not minimal.
+pub(crate) fn decode(buf: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &byte) in buf.iter().enumerate().take(MAX_LEN) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            // A trailing zero byte would make the encoding ambiguous.
+            if byte == 0 && i > 0 {
+                return None;
+            }
+            return Some((value, i + 1));
+        }
+    }
+
+    None
+}
+
+/// Reads a varint from a reader. Returns `None` if the reader is at its end
+/// before the first byte.
+pub(crate) fn read(reader: &mut impl Read) -> std::io::Result<Option<u64>> {
+    let mut buf = Vec::with_capacity(MAX_LEN);
+    let mut byte = [0];
+    loop {
+        if reader.read(&mut byte)? == 0 {
+            if buf.is_empty() {
+                return Ok(None);
+            }
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+
+        buf.push(byte[0]);
+        if byte[0] & 0x80 == 0 || buf.len() == MAX_LEN {
+            break;
+        }
+    }
+
+    match decode(&buf) {
+        Some((value, _)) => Ok(Some(value)),
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "invalid varint",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, read};
+
+    #[test]
+    fn test_varint() {
+        for (value, bytes) in [
+            (0, &[0x00][..]),
+            (1, &[0x01]),
+            (127, &[0x7f]),
+            (128, &[0x80, 0x01]),
+            (300, &[0xac, 0x02]),
+            (0x0401, &[0x81, 0x08]),
+        ] {
+            let mut out = Vec::new();
+            encode(value, &mut out);
+            assert_eq!(out, bytes);
+            assert_eq!(decode(bytes), Some((value, bytes.len())));
+            assert_eq!(read(&mut &bytes[..]).unwrap(), Some(value));
+        }
+
+        assert_eq!(decode(&[0x80]), None);
+        assert_eq!(decode(&[0x80, 0x00]), None);
+        assert_eq!(decode(&[0xff; 10]), None);
+        assert_eq!(read(&mut &[][..]).unwrap(), None);
+        assert!(read(&mut &[0x80][..]).is_err());
+    }
+}
@@ -1,7 +1,5 @@
-use crate::IpfsApi;
+use crate::{IpfsApi, Result};
 use serde_json;
-use std::error::Error;
-use ureq;
 
 pub struct IpfsVersion {
     val: serde_json::Value,
@@ -18,6 +16,10 @@ macro_rules! version_method_str {
 }
 
 impl IpfsVersion {
+    pub(crate) fn new(val: serde_json::Value) -> Self {
+        Self { val }
+    }
+
     version_method_str!(commit, Commit);
     version_method_str!(golang, Golang);
     version_method_str!(repo, Repo);
@@ -27,11 +29,9 @@ impl IpfsVersion {
 
 impl IpfsApi {
     /// Get the version from the IPFS daemon.
-    pub fn version(&self) -> Result<IpfsVersion, Box<dyn Error>> {
-        let url =
-            format!("http://{}:{}/api/v0/version", self.server, self.port);
-        let resp = ureq::post(&url).call()?;
+    pub fn version(&self) -> Result<IpfsVersion> {
+        let resp = self.post("version").call()?;
         let json_val = serde_json::from_reader(resp.into_reader())?;
-        Ok(IpfsVersion { val: json_val })
+        Ok(IpfsVersion::new(json_val))
     }
 }